@@ -0,0 +1,289 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use gitplay_core::cache::GitplayState;
+use serde::Serialize;
+use serde_json::Value;
+
+mod server;
+
+/*
+gitplay-cli opens a repository with the same backend as the desktop application and prints the
+results of its operations, so reports can be scripted without the GUI.
+ */
+#[derive(Parser)]
+#[command(
+    name = "gitplay-cli",
+    version,
+    about = "Explore the history of a Git repository"
+)]
+struct Cli {
+    /// Path to the Git repository
+    #[arg(short, long, default_value = ".")]
+    repository: PathBuf,
+
+    /// How the output is printed
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Json,
+    Table,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the commits of the timeline, oldest first
+    Commits {
+        #[arg(long)]
+        start: Option<usize>,
+        #[arg(long)]
+        count: Option<usize>,
+    },
+    /// List the tags with the frames they point at, oldest first
+    Tags,
+    /// Show the file tree of a frame
+    Tree {
+        /// Commit id or any revision, like HEAD
+        commit: String,
+        /// Path rules like "src/", "src/**" or "!target/**", the whole tree by default
+        #[arg(long = "path", default_value = "**")]
+        paths: Vec<String>,
+        /// Count the lines of every file listed, which reads all of them
+        #[arg(long)]
+        line_counts: bool,
+    },
+    /// List the commits which added, modified or deleted a file
+    History { path: String },
+    /// List the files modified most often, starting at the given frame
+    Hotspots {
+        #[arg(long)]
+        start: Option<usize>,
+    },
+    /// List lines added and deleted per file
+    Churn {
+        #[arg(long)]
+        start: Option<usize>,
+        #[arg(long)]
+        count: Option<usize>,
+    },
+    /// Show the language breakdown of each frame
+    Stats {
+        #[arg(long)]
+        start: Option<usize>,
+        #[arg(long)]
+        count: Option<usize>,
+    },
+    /// Print the changes of each commit in Gource's custom log format
+    Gource {
+        #[arg(long)]
+        start: Option<usize>,
+        #[arg(long)]
+        count: Option<usize>,
+        /// Write the log to this file instead of printing it
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Write the commits, file changes, authors and frame sizes as CSV and JSON files
+    Report {
+        #[arg(long)]
+        start: Option<usize>,
+        #[arg(long)]
+        count: Option<usize>,
+        /// Folder the files are written to
+        #[arg(short, long, default_value = "gitplay-report")]
+        output: String,
+    },
+    /// Write an HTML page with an overview, hotspots, contributors, directories and releases
+    HtmlReport {
+        #[arg(long)]
+        start: Option<usize>,
+        #[arg(long)]
+        count: Option<usize>,
+        #[arg(short, long, default_value = "gitplay-report.html")]
+        output: String,
+    },
+    /// Check that the steps of a tour point at commits, folders, files and lines that exist
+    ValidateTour {
+        /// Path of the tour file, like .gitplay/tours/onboarding.json
+        file: String,
+    },
+    /// Serve the operations of the desktop application as a JSON API on localhost
+    Serve {
+        #[arg(long, default_value_t = 7070)]
+        port: u16,
+        /// Origin of a web page allowed to call the API, like http://localhost:1420
+        #[arg(long = "allow-origin")]
+        allowed_origins: Vec<String>,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(err) = run(&cli) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), String> {
+    let repo = GitplayState::new();
+    repo.open(cli.repository.clone())?;
+    repo.prepare_cache()?;
+
+    match &cli.command {
+        Command::Commits { start, count } => {
+            print_output(cli.format, &repo.get_commit_summaries(*start, *count)?)
+        }
+        Command::Tags => print_output(cli.format, &repo.get_tags()?),
+        Command::Tree {
+            commit,
+            paths,
+            line_counts,
+        } => {
+            let paths: Vec<&str> = paths.iter().map(|x| x.as_str()).collect();
+            let frame =
+                serde_json::to_value(repo.get_commit_details(commit, paths, *line_counts)?)
+                    .map_err(|err| err.to_string())?;
+            // The table only lists the files and directories of the frame
+            match cli.format {
+                OutputFormat::Json => print_output(cli.format, &frame),
+                OutputFormat::Table => {
+                    print_output(cli.format, &frame["file_structure"]["blobs"].clone())
+                }
+            }
+        }
+        Command::History { path } => print_output(cli.format, &repo.get_file_history(path)?),
+        Command::Hotspots { start } => print_output(
+            cli.format,
+            &repo.get_files_ordered_by_most_modifications(*start)?,
+        ),
+        Command::Churn { start, count } => {
+            print_output(cli.format, &repo.get_file_churn(*start, *count)?)
+        }
+        Command::Stats { start, count } => {
+            print_output(cli.format, &repo.get_language_stats(*start, *count)?)
+        }
+        // The log is already in Gource's format, whatever the output format is
+        Command::Gource {
+            start,
+            count,
+            output,
+        } => match output {
+            Some(file_path) => {
+                let lines = repo.export_gource_log(file_path, *start, *count)?;
+                eprintln!("Wrote {} lines to {}", lines, file_path);
+                Ok(())
+            }
+            None => {
+                print!("{}", repo.get_gource_log(*start, *count)?);
+                Ok(())
+            }
+        },
+        Command::Report {
+            start,
+            count,
+            output,
+        } => print_output(cli.format, &repo.export_report(output, *start, *count)?),
+        Command::HtmlReport {
+            start,
+            count,
+            output,
+        } => print_output(
+            cli.format,
+            &repo.export_html_report(output, *start, *count)?,
+        ),
+        Command::ValidateTour { file } => {
+            repo.load_tour(file)?;
+            let problems = repo.validate_tour()?;
+            print_output(cli.format, &problems)?;
+            match problems.len() {
+                0 => Ok(()),
+                count => Err(format!("{} problems in {}", count, file)),
+            }
+        }
+        Command::Serve {
+            port,
+            allowed_origins,
+        } => server::serve(repo, *port, allowed_origins.clone()),
+    }
+}
+
+fn print_output<T: Serialize>(format: OutputFormat, output: &T) -> Result<(), String> {
+    let value = serde_json::to_value(output).map_err(|err| err.to_string())?;
+    match format {
+        OutputFormat::Json => match serde_json::to_string_pretty(&value) {
+            Ok(json) => println!("{}", json),
+            Err(err) => return Err(err.to_string()),
+        },
+        OutputFormat::Table => print_table(&value),
+    }
+    Ok(())
+}
+
+fn print_table(value: &Value) {
+    // Rows are the items of an array, columns are the fields of the first item
+    // Nested values are printed as compact JSON in their cell
+    let rows: Vec<Vec<String>> = match value {
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Object(fields) => fields.values().map(format_cell).collect(),
+                Value::Array(values) => values.iter().map(format_cell).collect(),
+                _ => vec![format_cell(item)],
+            })
+            .collect(),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| vec![key.clone(), format_cell(value)])
+            .collect(),
+        _ => vec![vec![format_cell(value)]],
+    };
+    let header: Option<Vec<String>> = match value {
+        Value::Array(items) => match items.first() {
+            Some(Value::Object(fields)) => Some(fields.keys().cloned().collect()),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let mut widths: Vec<usize> = Vec::new();
+    for row in header.iter().chain(rows.iter()) {
+        for (index, cell) in row.iter().enumerate() {
+            let width = cell.chars().count();
+            match widths.get_mut(index) {
+                Some(existing) => *existing = (*existing).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+    let print_row = |row: &Vec<String>| {
+        let cells: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| format!("{:width$}", cell, width = widths[index]))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    };
+    if let Some(header) = &header {
+        print_row(header);
+        print_row(&widths.iter().map(|x| "-".repeat(*x)).collect());
+    }
+    for row in &rows {
+        print_row(row);
+    }
+}
+
+fn format_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        // Only the first line of multi-line text fits in a cell
+        Value::String(text) => text.lines().next().unwrap_or("").to_owned(),
+        _ => value.to_string(),
+    }
+}
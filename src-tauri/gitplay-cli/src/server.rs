@@ -0,0 +1,628 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::Arc,
+    thread,
+};
+
+use gitplay_core::{
+    annotations::AnnotationTarget, buckets::BucketSize, cache::GitplayState, diff::MergeDiffMode,
+    tours::TourStep,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/*
+The server exposes the same operations as the Tauri commands of the desktop application, over
+HTTP on localhost. Each command is a POST to /api/<command name> with a JSON object of the
+arguments, named in camelCase like the frontend passes them to `invoke`. The response is the JSON
+of the command's output, or {"error": <message>} with an error status.
+
+Browsers send an Origin header with cross-origin requests. Since any web page could otherwise read
+the repository through this server, only the origins given with --allow-origin are answered.
+ */
+pub fn serve(repo: GitplayState, port: u16, allowed_origins: Vec<String>) -> Result<(), String> {
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|err| format!("Could not start server on port {}: {}", port, err))?;
+    println!("Serving on http://127.0.0.1:{}/api/", port);
+
+    let server = Arc::new(server);
+    let repo = Arc::new(repo);
+    let allowed_origins = Arc::new(allowed_origins);
+    let workers = thread::available_parallelism().map_or(1, |x| x.get());
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let repo = Arc::clone(&repo);
+            let allowed_origins = Arc::clone(&allowed_origins);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle_request(request, &repo, &allowed_origins);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+    Ok(())
+}
+
+fn handle_request(
+    mut request: Request,
+    repo: &GitplayState,
+    allowed_origins: &[String],
+) -> (u16, Value) {
+    // Returns the status and the JSON of the response, which has been sent
+    let origin = request
+        .headers()
+        .iter()
+        .find(|x| x.field.equiv("Origin"))
+        .map(|x| x.value.to_string());
+    if let Some(origin) = &origin {
+        if !allowed_origins.contains(origin) {
+            let error = json!({ "error": "Origin is not allowed" });
+            respond(request, 403, &error, None);
+            return (403, error);
+        }
+    }
+
+    // Preflight requests of browsers, for the JSON content type
+    if *request.method() == Method::Options {
+        respond(request, 204, &Value::Null, origin.as_deref());
+        return (204, Value::Null);
+    }
+    let command = match request.url().strip_prefix("/api/") {
+        Some(command) if *request.method() == Method::Post => command.to_owned(),
+        _ => {
+            let error = json!({ "error": "Use POST /api/<command>" });
+            respond(request, 404, &error, origin.as_deref());
+            return (404, error);
+        }
+    };
+
+    let mut body = String::new();
+    if let Err(err) = request.as_reader().read_to_string(&mut body) {
+        let error = json!({ "error": err.to_string() });
+        respond(request, 400, &error, origin.as_deref());
+        return (400, error);
+    }
+    // An empty body is the same as no arguments
+    let parsed = match body.trim() {
+        "" => Ok(json!({})),
+        body => serde_json::from_str(body),
+    };
+    let args = match parsed {
+        Ok(args) => args,
+        Err(err) => {
+            let error = json!({ "error": err.to_string() });
+            respond(request, 400, &error, origin.as_deref());
+            return (400, error);
+        }
+    };
+
+    let result = catch_panics(|| run_command(repo, &command, args));
+    let (status, output) = match result {
+        Ok(Ok(Some(output))) => (200, output),
+        Ok(Ok(None)) => (
+            404,
+            json!({ "error": format!("Unknown command {}", command) }),
+        ),
+        Ok(Err(err)) => (get_error_status(&err), json!({ "error": err })),
+        Err(err) => (500, json!({ "error": err })),
+    };
+    println!("{}, {:?} completed", command, status);
+    respond(request, status, &output, origin.as_deref());
+    (status, output)
+}
+
+fn catch_panics<T, F>(run: F) -> Result<T, String>
+where
+    F: FnOnce() -> T,
+{
+    // A panic in a command must not take down the worker thread handling requests, it is
+    // answered as an internal error. The state stays usable, its poisoned locks are still taken
+    match panic::catch_unwind(AssertUnwindSafe(run)) {
+        Ok(result) => Ok(result),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|x| x.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_owned());
+            Err(format!("Internal error: {}", message))
+        }
+    }
+}
+
+fn get_error_status(error: &str) -> u16 {
+    // Commands fail on what they were asked for, like bad arguments, revisions, paths or
+    // patterns. Commands which need an open repository or a loaded timeline first are conflicts
+    // with the state of the session
+    match error {
+        "Repository path is not set" | "Cache not prepared" | "No tour is loaded" => 409,
+        _ => 400,
+    }
+}
+
+fn respond(request: Request, status: u16, output: &Value, origin: Option<&str>) {
+    let body = match output {
+        Value::Null if status == 204 => String::new(),
+        _ => output.to_string(),
+    };
+    let mut response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"));
+    if let Some(origin) = origin {
+        response = response
+            .with_header(header("Access-Control-Allow-Origin", origin))
+            .with_header(header("Access-Control-Allow-Methods", "POST, OPTIONS"))
+            .with_header(header("Access-Control-Allow-Headers", "Content-Type"))
+            .with_header(header("Vary", "Origin"));
+    }
+    let _ = request.respond(response);
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenRepositoryArgs {
+    path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RangeArgs {
+    start_index: Option<usize>,
+    count: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitDetailsArgs {
+    commit_id: String,
+    requested_folders: Vec<String>,
+    with_line_counts: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimestampArgs {
+    timestamp: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimeBucketsArgs {
+    size: BucketSize,
+    include_empty: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitDiffArgs {
+    commit_id: String,
+    mode: Option<MergeDiffMode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadFileContentsArgs {
+    object_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SizesForPathsArgs {
+    requested_folders: Vec<String>,
+    start_index: Option<usize>,
+    count: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileHistoryArgs {
+    file_path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitsChangingStringArgs {
+    pattern: String,
+    is_regex: bool,
+    path_filters: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GrepArgs {
+    commit_id: String,
+    pattern: String,
+    is_regex: bool,
+    path_globs: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SymbolsAtCommitArgs {
+    commit_id: String,
+    path_filters: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SymbolTimelineArgs {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportFileArgs {
+    file_path: String,
+    start_index: Option<usize>,
+    count: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportReportArgs {
+    folder_path: String,
+    start_index: Option<usize>,
+    count: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateTourArgs {
+    name: String,
+    file_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FilePathArgs {
+    file_path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateTourArgs {
+    name: String,
+    description: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TourStepArgs {
+    step: TourStep,
+    step_index: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateTourStepArgs {
+    step_index: usize,
+    step: TourStep,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StepIndexArgs {
+    step_index: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitIndexArgs {
+    commit_index: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddAnnotationArgs {
+    target: AnnotationTarget,
+    text: String,
+    shared: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateAnnotationArgs {
+    id: String,
+    text: String,
+    shared: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AnnotationIdArgs {
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotesRefsArgs {
+    notes_refs: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitNoteArgs {
+    commit_id: String,
+    notes_ref: Option<String>,
+    message: String,
+}
+
+fn run_command(repo: &GitplayState, command: &str, args: Value) -> Result<Option<Value>, String> {
+    // Commands have the names and arguments of the Tauri commands in src-tauri/src/main.rs
+    let output = match command {
+        "open_repository" => {
+            let args: OpenRepositoryArgs = parse_args(args)?;
+            to_json(repo.open(PathBuf::from(args.path))?)
+        }
+        "prepare_cache" => to_json(repo.prepare_cache()?),
+        "get_commits" => {
+            let args: RangeArgs = parse_args(args)?;
+            to_json(repo.get_commits(args.start_index, args.count)?)
+        }
+        "get_commit_summaries" => {
+            let args: RangeArgs = parse_args(args)?;
+            to_json(repo.get_commit_summaries(args.start_index, args.count)?)
+        }
+        "find_commit_at_time" => {
+            let args: TimestampArgs = parse_args(args)?;
+            to_json(repo.find_commit_at_time(args.timestamp)?)
+        }
+        "get_time_buckets" => {
+            let args: TimeBucketsArgs = parse_args(args)?;
+            to_json(repo.get_time_buckets(args.size, args.include_empty.unwrap_or(false))?)
+        }
+        "get_commit_graph" => {
+            let args: RangeArgs = parse_args(args)?;
+            to_json(repo.get_commit_graph(args.start_index, args.count)?)
+        }
+        "get_commit_details" => {
+            let args: CommitDetailsArgs = parse_args(args)?;
+            to_json(repo.get_commit_details(
+                &args.commit_id,
+                as_strs(&args.requested_folders),
+                args.with_line_counts.unwrap_or(false),
+            )?)
+        }
+        "get_commit_diff" => {
+            let args: CommitDiffArgs = parse_args(args)?;
+            to_json(repo.get_commit_diff(&args.commit_id, args.mode.unwrap_or_default())?)
+        }
+        "read_file_contents" => {
+            let args: ReadFileContentsArgs = parse_args(args)?;
+            to_json(repo.read_file_contents(&args.object_id)?)
+        }
+        "get_sizes_for_paths" => {
+            let args: SizesForPathsArgs = parse_args(args)?;
+            to_json(repo.get_sizes_for_paths(
+                as_strs(&args.requested_folders),
+                args.start_index,
+                args.count,
+            )?)
+        }
+        "get_files_ordered_by_most_modifications" => {
+            let args: RangeArgs = parse_args(args)?;
+            to_json(repo.get_files_ordered_by_most_modifications(args.start_index)?)
+        }
+        "get_file_history" => {
+            let args: FileHistoryArgs = parse_args(args)?;
+            to_json(repo.get_file_history(&args.file_path)?)
+        }
+        "get_file_churn" => {
+            let args: RangeArgs = parse_args(args)?;
+            to_json(repo.get_file_churn(args.start_index, args.count)?)
+        }
+        "find_commits_changing_string" => {
+            let args: CommitsChangingStringArgs = parse_args(args)?;
+            to_json(repo.find_commits_changing_string(
+                &args.pattern,
+                args.is_regex,
+                as_strs(&args.path_filters),
+            )?)
+        }
+        "grep_at_commit" => {
+            let args: GrepArgs = parse_args(args)?;
+            to_json(repo.grep_at_commit(
+                &args.commit_id,
+                &args.pattern,
+                args.is_regex,
+                as_strs(&args.path_globs),
+            )?)
+        }
+        "get_symbols_at_commit" => {
+            let args: SymbolsAtCommitArgs = parse_args(args)?;
+            to_json(repo.get_symbols_at_commit(&args.commit_id, as_strs(&args.path_filters))?)
+        }
+        "get_symbol_timeline" => {
+            let args: SymbolTimelineArgs = parse_args(args)?;
+            to_json(repo.get_symbol_timeline(&args.name)?)
+        }
+        "get_language_stats" => {
+            let args: RangeArgs = parse_args(args)?;
+            to_json(repo.get_language_stats(args.start_index, args.count)?)
+        }
+        "export_gource_log" => {
+            let args: ExportFileArgs = parse_args(args)?;
+            to_json(repo.export_gource_log(&args.file_path, args.start_index, args.count)?)
+        }
+        "export_report" => {
+            let args: ExportReportArgs = parse_args(args)?;
+            to_json(repo.export_report(&args.folder_path, args.start_index, args.count)?)
+        }
+        "export_html_report" => {
+            let args: ExportFileArgs = parse_args(args)?;
+            to_json(repo.export_html_report(&args.file_path, args.start_index, args.count)?)
+        }
+        "list_tours" => to_json(repo.list_tours()?),
+        "create_tour" => {
+            let args: CreateTourArgs = parse_args(args)?;
+            to_json(repo.create_tour(&args.name, args.file_path.as_deref())?)
+        }
+        "load_tour" => {
+            let args: FilePathArgs = parse_args(args)?;
+            to_json(repo.load_tour(&args.file_path)?)
+        }
+        "get_active_tour" => to_json(repo.get_active_tour()),
+        "update_tour" => {
+            let args: UpdateTourArgs = parse_args(args)?;
+            to_json(repo.update_tour(&args.name, &args.description)?)
+        }
+        "add_tour_step" => {
+            let args: TourStepArgs = parse_args(args)?;
+            to_json(repo.add_tour_step(args.step, args.step_index)?)
+        }
+        "update_tour_step" => {
+            let args: UpdateTourStepArgs = parse_args(args)?;
+            to_json(repo.update_tour_step(args.step_index, args.step)?)
+        }
+        "remove_tour_step" => {
+            let args: StepIndexArgs = parse_args(args)?;
+            to_json(repo.remove_tour_step(args.step_index)?)
+        }
+        "validate_tour" => to_json(repo.validate_tour()?),
+        "go_to_tour_step" => {
+            let args: StepIndexArgs = parse_args(args)?;
+            to_json(repo.go_to_tour_step(args.step_index)?)
+        }
+        "next_tour_step" => to_json(repo.next_tour_step()?),
+        "previous_tour_step" => to_json(repo.previous_tour_step()?),
+        "list_annotations" => to_json(repo.list_annotations()?),
+        "get_annotations_at_commit" => {
+            let args: CommitIndexArgs = parse_args(args)?;
+            to_json(repo.get_annotations_at_commit(args.commit_index)?)
+        }
+        "add_annotation" => {
+            let args: AddAnnotationArgs = parse_args(args)?;
+            to_json(repo.add_annotation(args.target, &args.text, args.shared)?)
+        }
+        "update_annotation" => {
+            let args: UpdateAnnotationArgs = parse_args(args)?;
+            to_json(repo.update_annotation(&args.id, &args.text, args.shared)?)
+        }
+        "remove_annotation" => {
+            let args: AnnotationIdArgs = parse_args(args)?;
+            to_json(repo.remove_annotation(&args.id)?)
+        }
+        "get_notes_refs" => to_json(repo.get_notes_refs()),
+        "set_notes_refs" => {
+            let args: NotesRefsArgs = parse_args(args)?;
+            to_json(repo.set_notes_refs(as_strs(&args.notes_refs))?)
+        }
+        "list_notes_refs" => to_json(repo.list_notes_refs()?),
+        "set_commit_note" => {
+            let args: CommitNoteArgs = parse_args(args)?;
+            to_json(repo.set_commit_note(
+                &args.commit_id,
+                args.notes_ref.as_deref(),
+                &args.message,
+            )?)
+        }
+        "get_tags" => to_json(repo.get_tags()?),
+        _ => return Ok(None),
+    };
+    output.map(Some)
+}
+
+fn parse_args<T: DeserializeOwned>(args: Value) -> Result<T, String> {
+    serde_json::from_value(args).map_err(|err| format!("Invalid arguments: {}", err))
+}
+
+fn to_json<T: Serialize>(output: T) -> Result<Value, String> {
+    serde_json::to_value(output).map_err(|err| err.to_string())
+}
+
+fn as_strs(values: &[String]) -> Vec<&str> {
+    values.iter().map(|x| x.as_str()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tiny_http::TestRequest;
+
+    fn post(repo: &GitplayState, command: &str, body: &'static str) -> (u16, Value) {
+        let request = TestRequest::new()
+            .with_method(Method::Post)
+            .with_path(&format!("/api/{}", command))
+            .with_body(body);
+        handle_request(request.into(), repo, &[])
+    }
+
+    #[test]
+    fn commands_answer_errors_instead_of_panicking() {
+        let folder = tempfile::TempDir::new().unwrap();
+        git2::Repository::init(folder.path()).unwrap();
+        let repo = GitplayState::new();
+        let body = json!({ "path": folder.path() }).to_string();
+        assert_eq!(post(&repo, "open_repository", body.leak()).0, 200);
+
+        let (status, output) = post(&repo, "get_commit_summaries", "");
+        assert_eq!(status, 409);
+        assert_eq!(output["error"], "Cache not prepared");
+        let folder_path = folder.path().join("report");
+        let body = json!({ "folderPath": folder_path }).to_string();
+        assert_eq!(post(&repo, "export_report", body.leak()).0, 409);
+        assert!(!folder_path.exists());
+        assert_eq!(post(&repo, "no_such_command", "").0, 404);
+        assert_eq!(post(&repo, "get_commits", "not json").0, 400);
+        assert_eq!(post(&repo, "get_commits", r#"{"count": "all"}"#).0, 400);
+        let body = json!({ "path": folder.path().join("missing") }).to_string();
+        assert_eq!(post(&repo, "open_repository", body.leak()).0, 400);
+        // The state is still usable after the errors
+        assert_eq!(post(&repo, "get_notes_refs", "").0, 200);
+    }
+
+    #[test]
+    fn requests_from_other_origins_are_rejected() {
+        let repo = GitplayState::new();
+        let request = TestRequest::new()
+            .with_method(Method::Post)
+            .with_path("/api/get_notes_refs")
+            .with_header(header("Origin", "https://example.com"));
+        assert_eq!(handle_request(request.into(), &repo, &[]).0, 403);
+    }
+
+    #[test]
+    fn panics_are_answered_as_errors() {
+        let result: Result<(), String> = catch_panics(|| panic!("index out of bounds"));
+        assert_eq!(result.unwrap_err(), "Internal error: index out of bounds");
+        let result = catch_panics(|| Err::<(), String>(format!("{} failed", "command")));
+        assert_eq!(result.unwrap(), Err("command failed".to_owned()));
+    }
+
+    #[test]
+    fn requests_are_answered_after_a_panic() {
+        let folder = tempfile::TempDir::new().unwrap();
+        git2::Repository::init(folder.path()).unwrap();
+        let repo = GitplayState::new();
+        let body = json!({ "path": folder.path() }).to_string();
+        assert_eq!(post(&repo, "open_repository", body.leak()).0, 200);
+
+        // A command panicking on another worker thread, after using the state
+        let result = thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    catch_panics(|| {
+                        repo.prepare_cache().unwrap();
+                        panic!("Command failed")
+                    })
+                })
+                .join()
+                .unwrap()
+        });
+        let result: Result<(), String> = result;
+        assert_eq!(result.unwrap_err(), "Internal error: Command failed");
+        assert_eq!(post(&repo, "get_commit_summaries", "").0, 200);
+        assert_eq!(post(&repo, "get_notes_refs", "").0, 200);
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use git2::{ObjectType, Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::notes::write_note;
+
+/*
+Annotations are notes about the history written by the people exploring it, like "the great
+refactor of 2021". An annotation is attached to a commit, to a path at a commit or to a range of
+commits, and is shown when the timeline is at a frame it covers.
+
+They are kept per repository in the Git directory (gitplay/annotations.json), so they never show
+up in the working tree. Shared annotations are also written as Git notes under refs/notes/gitplay,
+attached to their first commit, and travel with the repository when that ref is pushed and
+fetched.
+ */
+
+pub const ANNOTATIONS_NOTES_REF: &str = "refs/notes/gitplay";
+const ANNOTATIONS_FILE_VERSION: usize = 1;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind")]
+pub enum AnnotationTarget {
+    Commit {
+        commit_id: String,
+    },
+    Path {
+        commit_id: String,
+        path: String,
+    },
+    // Both ends are included
+    Range {
+        from_commit_id: String,
+        to_commit_id: String,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Annotation {
+    pub id: String,
+    pub target: AnnotationTarget,
+    pub text: String,
+    pub created_at: i64,
+    // Also written as a Git note, so it can be shared with the repository
+    #[serde(default)]
+    pub shared: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+struct AnnotationsFile {
+    version: usize,
+    annotations: Vec<Annotation>,
+}
+
+impl AnnotationTarget {
+    pub fn get_anchor_commit_id(&self) -> &str {
+        // The commit a shared annotation is written to as a Git note
+        match self {
+            AnnotationTarget::Commit { commit_id } => commit_id,
+            AnnotationTarget::Path { commit_id, .. } => commit_id,
+            AnnotationTarget::Range { from_commit_id, .. } => from_commit_id,
+        }
+    }
+
+    pub fn is_visible_at(&self, commit_index: usize, commit_ids: &HashMap<String, usize>) -> bool {
+        // Whether the frame at the given index of the timeline is covered, commits which are not
+        // in the timeline are never visible
+        match self {
+            AnnotationTarget::Commit { commit_id } | AnnotationTarget::Path { commit_id, .. } => {
+                commit_ids.get(commit_id) == Some(&commit_index)
+            }
+            AnnotationTarget::Range {
+                from_commit_id,
+                to_commit_id,
+            } => match (commit_ids.get(from_commit_id), commit_ids.get(to_commit_id)) {
+                (Some(from_index), Some(to_index)) => {
+                    (*from_index.min(to_index)..=*from_index.max(to_index)).contains(&commit_index)
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+fn get_annotations_path(repository: &Repository) -> PathBuf {
+    repository.path().join("gitplay").join("annotations.json")
+}
+
+pub fn load_annotations(repository: &Repository) -> Result<Vec<Annotation>, String> {
+    // Annotations stored locally, then the shared ones found only in Git notes, like those fetched
+    // from a remote
+    let file_path = get_annotations_path(repository);
+    let mut output: Vec<Annotation> = match fs::read_to_string(&file_path) {
+        Ok(contents) => match serde_json::from_str::<AnnotationsFile>(&contents) {
+            Ok(file) => file.annotations,
+            Err(err) => return Err(format!("Could not read {}: {}", file_path.display(), err)),
+        },
+        Err(_) => Vec::new(),
+    };
+    for annotation in load_shared_annotations(repository) {
+        if !output.iter().any(|x| x.id == annotation.id) {
+            output.push(annotation);
+        }
+    }
+    output.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+    Ok(output)
+}
+
+fn load_shared_annotations(repository: &Repository) -> Vec<Annotation> {
+    // Notes that are not lists of annotations are skipped
+    let mut output: Vec<Annotation> = Vec::new();
+    let notes = match repository.notes(Some(ANNOTATIONS_NOTES_REF)) {
+        Ok(notes) => notes,
+        Err(_) => return output,
+    };
+    for (_, annotated_id) in notes.flatten() {
+        if let Ok(note) = repository.find_note(Some(ANNOTATIONS_NOTES_REF), annotated_id) {
+            if let Ok(annotations) = serde_json::from_slice::<Vec<Annotation>>(note.message_bytes())
+            {
+                output.extend(annotations);
+            }
+        }
+    }
+    output
+}
+
+pub fn save_annotations(
+    repository: &Repository,
+    annotations: &[Annotation],
+    changed_anchors: &[String],
+) -> Result<(), String> {
+    // Write all annotations to the local file, and rewrite the Git notes of the commits whose
+    // shared annotations changed
+    let file_path = get_annotations_path(repository);
+    if let Some(folder) = file_path.parent() {
+        if let Err(err) = fs::create_dir_all(folder) {
+            return Err(format!("Could not create {}: {}", folder.display(), err));
+        }
+    }
+    let file = AnnotationsFile {
+        version: ANNOTATIONS_FILE_VERSION,
+        annotations: annotations.to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(|err| err.to_string())?;
+    if let Err(err) = fs::write(&file_path, json + "\n") {
+        return Err(format!("Could not write {}: {}", file_path.display(), err));
+    }
+
+    for anchor in changed_anchors {
+        let shared: Vec<&Annotation> = annotations
+            .iter()
+            .filter(|x| x.shared && x.target.get_anchor_commit_id() == anchor)
+            .collect();
+        write_shared_note(repository, anchor, &shared)?;
+    }
+    Ok(())
+}
+
+fn write_shared_note(
+    repository: &Repository,
+    commit_id: &str,
+    annotations: &[&Annotation],
+) -> Result<(), String> {
+    // Without shared annotations left on the commit, its note is removed
+    let message = if annotations.is_empty() {
+        String::new()
+    } else {
+        serde_json::to_string_pretty(annotations).map_err(|err| err.to_string())?
+    };
+    write_note(repository, ANNOTATIONS_NOTES_REF, commit_id, &message)
+}
+
+pub fn new_annotation_id(target: &AnnotationTarget, text: &str, created_at: i64) -> String {
+    // Ids only need to be unique within a repository, a hash of the annotation and the current
+    // time in nanoseconds is plenty
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_nanos());
+    let seed = format!("{:?}\n{}\n{}\n{}", target, text, created_at, nanos);
+    match Oid::hash_object(ObjectType::Blob, seed.as_bytes()) {
+        Ok(oid) => oid.to_string()[..12].to_owned(),
+        Err(_) => format!("{:012x}", nanos % (1 << 48)),
+    }
+}
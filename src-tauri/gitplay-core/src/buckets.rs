@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dates::{civil_from_days, days_from_civil, format_date, SECONDS_PER_DAY},
+    diff::FileChurn,
+};
+
+/*
+Time buckets group the timeline by calendar periods instead of commits, so playback can move one
+week (or hour, day, month) per step on repositories where commits come in bursts. Buckets are in
+UTC and weeks start on Monday.
+
+The timeline is in topological order, and commit times are not always increasing along it (like
+rebased or cherry-picked commits). A commit goes in the bucket of the latest time seen so far, so
+each bucket is a continuous range of frames and buckets never go back in time.
+ */
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketSize {
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TimeBucket {
+    // Like "2023-07-14 13:00", "2023-07-14", "2023-07-10" for the week starting that Monday or
+    // "2023-07"
+    pub label: String,
+    // Start is included, end is not
+    pub start_time: i64,
+    pub end_time: i64,
+    // Frames in this bucket, both ends included. Empty buckets have no frames of their own and
+    // show the last frame of the bucket before them
+    pub first_commit_index: Option<usize>,
+    pub last_commit_index: usize,
+    pub last_commit_id: String,
+    pub commits: usize,
+    pub additions: usize,
+    pub deletions: usize,
+    // Changes of all commits in the bucket by file, files with the most churn first
+    pub file_changes: Vec<FileChurn>,
+}
+
+impl BucketSize {
+    pub fn get_start(&self, time: i64) -> i64 {
+        // Start of the bucket containing the given time
+        let days = time.div_euclid(SECONDS_PER_DAY);
+        match self {
+            BucketSize::Hour => time - time.rem_euclid(3600),
+            BucketSize::Day => days * SECONDS_PER_DAY,
+            // 1970-01-01 was a Thursday, three days after a Monday
+            BucketSize::Week => (days - (days + 3).rem_euclid(7)) * SECONDS_PER_DAY,
+            BucketSize::Month => {
+                let (year, month, _) = civil_from_days(days);
+                days_from_civil(year, month, 1) * SECONDS_PER_DAY
+            }
+        }
+    }
+
+    pub fn get_end(&self, start_time: i64) -> i64 {
+        // Start of the next bucket, from the start of a bucket
+        match self {
+            BucketSize::Hour => start_time + 3600,
+            BucketSize::Day => start_time + SECONDS_PER_DAY,
+            BucketSize::Week => start_time + 7 * SECONDS_PER_DAY,
+            BucketSize::Month => {
+                let (year, month, _) = civil_from_days(start_time.div_euclid(SECONDS_PER_DAY));
+                let (year, month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                days_from_civil(year, month, 1) * SECONDS_PER_DAY
+            }
+        }
+    }
+
+    pub fn get_label(&self, start_time: i64) -> String {
+        match self {
+            BucketSize::Hour => format!(
+                "{} {:02}:00",
+                format_date(start_time),
+                start_time.rem_euclid(SECONDS_PER_DAY) / 3600
+            ),
+            BucketSize::Day | BucketSize::Week => format_date(start_time),
+            BucketSize::Month => format_date(start_time)[..7].to_owned(),
+        }
+    }
+}
+
+// Listing empty buckets follows the calendar rather than the commits, so a gap of years between
+// two commits would make millions of hourly buckets
+pub const MAX_EMPTY_BUCKETS: usize = 10_000;
+
+// Start time of a bucket and the first and last commit indices in it, None for empty buckets
+pub type BucketRange = (i64, Option<(usize, usize)>);
+
+pub fn get_bucket_ranges(
+    times: &[i64],
+    size: BucketSize,
+    include_empty: bool,
+) -> Result<Vec<BucketRange>, String> {
+    // Start time and range of commit indices of each bucket, for the times of the commits in
+    // timeline order. Empty buckets between two commits are listed only when asked for, up to
+    // MAX_EMPTY_BUCKETS in total
+    let mut output: Vec<BucketRange> = Vec::new();
+    let mut latest_time = i64::MIN;
+    let mut empty_count: usize = 0;
+    for (index, time) in times.iter().enumerate() {
+        latest_time = latest_time.max(*time);
+        let start_time = size.get_start(latest_time);
+        match output.last_mut() {
+            Some((last_start_time, Some((_, last_index)))) if *last_start_time == start_time => {
+                *last_index = index;
+            }
+            Some((last_start_time, _)) => {
+                if include_empty {
+                    let mut empty_start_time = size.get_end(*last_start_time);
+                    while empty_start_time < start_time {
+                        empty_count += 1;
+                        if empty_count > MAX_EMPTY_BUCKETS {
+                            return Err(format!(
+                                "More than {} empty buckets, choose a larger bucket size",
+                                MAX_EMPTY_BUCKETS
+                            ));
+                        }
+                        output.push((empty_start_time, None));
+                        empty_start_time = size.get_end(empty_start_time);
+                    }
+                }
+                output.push((start_time, Some((index, index))));
+            }
+            None => output.push((start_time, Some((index, index)))),
+        }
+    }
+    Ok(output)
+}
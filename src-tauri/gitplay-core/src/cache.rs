@@ -0,0 +1,2382 @@
+use std::{
+    cmp::min,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use git2::{Oid, Repository};
+
+use crate::{
+    annotations::{self, new_annotation_id, Annotation, AnnotationTarget},
+    buckets::{get_bucket_ranges, BucketSize, TimeBucket},
+    diff::{
+        self, get_churn_by_path, get_diff_with_first_parent, CommitDiff, FileChurn, MergeDiffMode,
+    },
+    export::{get_frame_size, get_gource_lines, AuthorActivity, Report},
+    graph::{self, GraphRow},
+    html_report::{get_directory_sizes, render_html_report, HtmlReport},
+    languages::{FrameLanguageStats, LanguageStatsCache},
+    notes::{self, CommitNote, DEFAULT_NOTES_REF},
+    object_cache::ObjectCache,
+    path_filter::PathFilter,
+    scan::scan_commits,
+    search::{self, get_paths_matching_pickaxe, GrepMatch, Pickaxe, PickaxeMatch},
+    symbols::{FileSymbols, SymbolEvent, SymbolIndex},
+    tags::{self, Tag},
+    tours::{self, ActiveTour, Tour, TourFile, TourProblem, TourStep, TourStepView},
+    walker::{self, get_file_hashes_for_paths_in_commit, CommitFrame, CommitSummary},
+};
+
+/// A session on one Git repository, shared between the commands of the desktop application and
+/// the command line interface.
+///
+/// Open a repository with [`GitplayState::open`], then load its timeline with
+/// [`GitplayState::prepare_cache`]. Commits are addressed by their index in the timeline, oldest
+/// first.
+///
+/// A command which panics poisons the locks it holds. The state stays usable after that, like
+/// after an error: locks are still taken, and preparing the cache again rebuilds the timeline.
+pub struct GitplayState {
+    repository_path: Mutex<Option<PathBuf>>,
+    commits: Mutex<Vec<CommitFrame>>,
+    branch_names: Mutex<Vec<String>>,
+    tags: Mutex<Vec<Tag>>,
+    // Row of the commit graph for each commit of the timeline
+    graph: Mutex<Vec<GraphRow>>,
+    commit_ids: Mutex<HashMap<String, usize>>,
+    // Latest commit time up to each index of the timeline, sorted even when commit times are not
+    commit_times: Mutex<Vec<i64>>,
+    commits_count: Mutex<Option<usize>>,
+    symbol_index: Mutex<SymbolIndex>,
+    language_stats: Mutex<LanguageStatsCache>,
+    tour: Mutex<Option<ActiveTour>>,
+    // Notes refs read into commit frames, none by default
+    notes_refs: Mutex<Vec<String>>,
+    object_cache: Mutex<ObjectCache>,
+
+    last_error_message: Mutex<Option<String>>,
+}
+
+impl Default for GitplayState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GitplayState {
+    /// A session without a repository, call [`GitplayState::open`] first.
+    pub fn new() -> Self {
+        GitplayState {
+            repository_path: Mutex::new(None),
+            commits: Mutex::new(Vec::new()),
+            branch_names: Mutex::new(Vec::new()),
+            tags: Mutex::new(Vec::new()),
+            graph: Mutex::new(Vec::new()),
+            commit_ids: Mutex::new(HashMap::new()),
+            commit_times: Mutex::new(Vec::new()),
+            commits_count: Mutex::new(None),
+            symbol_index: Mutex::new(SymbolIndex::new()),
+            language_stats: Mutex::new(LanguageStatsCache::new()),
+            tour: Mutex::new(None),
+            notes_refs: Mutex::new(Vec::new()),
+            object_cache: Mutex::new(ObjectCache::new()),
+
+            last_error_message: Mutex::new(None),
+        }
+    }
+
+    /// Open the repository at the given path, forgetting everything cached for the previous one.
+    pub fn open(&self, path: PathBuf) -> Result<String, String> {
+        match Repository::open(&path) {
+            Ok(repository) => {
+                *self
+                    .repository_path
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(PathBuf::from(&path));
+                // Reset the vector of commits and everything indexed by it
+                *self.commits.lock().unwrap_or_else(PoisonError::into_inner) = Vec::new();
+                *self
+                    .commit_ids
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = HashMap::new();
+                *self
+                    .commits_count
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = None;
+                *self
+                    .branch_names
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Vec::new();
+                *self.tags.lock().unwrap_or_else(PoisonError::into_inner) = Vec::new();
+                *self
+                    .commit_times
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Vec::new();
+                *self.graph.lock().unwrap_or_else(PoisonError::into_inner) = Vec::new();
+                *self
+                    .symbol_index
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = SymbolIndex::new();
+                *self
+                    .language_stats
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = LanguageStatsCache::new();
+                *self.tour.lock().unwrap_or_else(PoisonError::into_inner) = None;
+                *self
+                    .notes_refs
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) =
+                    notes::read_configured_notes_refs(&repository);
+                *self
+                    .object_cache
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = ObjectCache::new();
+                Ok("Repository path is valid".to_owned())
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// Load the timeline of the repository: its commits reachable from HEAD, oldest first, with
+    /// their refs, tags and commit graph. Returns the number of commits and their ids in order.
+    pub fn prepare_cache(&self) -> Result<(usize, Vec<String>), String> {
+        // Read all the commits in the repository and cache them in our GitplayState data structure
+        // While we read all the commits, we also make a vector of all files that are present in the entire repository
+        // For each file, we also store the commit indices where there are changes to those files
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => {
+                // Get all the commits from walker, with everything indexed by them. Nothing is
+                // stored before all of it is read, so a failure keeps the previous timeline whole
+                let all_commits = walker::load_all_commits(&repository);
+                match all_commits {
+                    Ok(mut commits_vec) => {
+                        // Refs pointing at each commit, like `git log --decorate`
+                        let mut decorations = walker::get_decorations(&repository);
+                        for commit in commits_vec.iter_mut() {
+                            if let Some(commit_decorations) = decorations.remove(&commit.get_id()) {
+                                commit.set_decorations(commit_decorations);
+                            }
+                        }
+                        let len = commits_vec.len();
+                        // Extract commit SHA hashes and store them separately in our GitplayState data structure
+                        let commit_ids: HashMap<String, usize> = commits_vec
+                            .iter()
+                            .enumerate()
+                            .map(|(i, x)| (x.get_id(), i))
+                            .collect();
+                        // Tags are resolved to the indices of the commits we just read
+                        let tags = match tags::get_tags(&repository, &commit_ids) {
+                            Ok(tags) => tags,
+                            Err(err) => {
+                                *self
+                                    .last_error_message
+                                    .lock()
+                                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                                return Err(err);
+                            }
+                        };
+                        let commit_times: Vec<i64> = commits_vec
+                            .iter()
+                            .scan(i64::MIN, |latest_time, x| {
+                                *latest_time = (*latest_time).max(x.get_time());
+                                Some(*latest_time)
+                            })
+                            .collect();
+                        let graph = graph::get_graph_layout(&commits_vec, &commit_ids);
+                        let commit_hashes_in_order =
+                            commits_vec.iter().map(|x| x.get_id()).collect();
+
+                        // Store all the branch names of this repository in our GitplayState data structure
+                        *self
+                            .branch_names
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner) =
+                            walker::get_all_branch_names(&repository);
+                        *self
+                            .commit_ids
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner) = commit_ids;
+                        *self
+                            .commit_times
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner) = commit_times;
+                        *self.graph.lock().unwrap_or_else(PoisonError::into_inner) = graph;
+                        *self.commits.lock().unwrap_or_else(PoisonError::into_inner) = commits_vec;
+                        *self.tags.lock().unwrap_or_else(PoisonError::into_inner) = tags;
+                        *self
+                            .commits_count
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner) = Some(len);
+                        Ok((len, commit_hashes_in_order))
+                    }
+                    Err(err) => {
+                        *self
+                            .commits_count
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner) = None;
+                        Err(err)
+                    }
+                }
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// Ids and messages of a range of the timeline, 100 commits from the start by default.
+    pub fn get_commits(
+        &self,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<HashMap<String, String>, String> {
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let end_index = start_index
+            .unwrap_or(0)
+            .saturating_add(count.unwrap_or(100))
+            .min(self.get_commits_count()?);
+        let mut output: HashMap<String, String> = HashMap::new();
+
+        let commits = self.commits.lock().unwrap_or_else(PoisonError::into_inner);
+        for commit in commits[start_index.unwrap_or(0).min(end_index)..end_index].iter() {
+            let summary = commit.get_summary();
+            output.insert(summary.0, summary.1);
+        }
+        Ok(output)
+    }
+
+    /// Summaries of a range of the timeline, in timeline order.
+    pub fn get_commit_summaries(
+        &self,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<Vec<CommitSummary>, String> {
+        // Same range as `get_commits`, but in timeline order and with the index of each commit
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let start_index = start_index.unwrap_or(0);
+        let end_index = start_index
+            .saturating_add(count.unwrap_or(100))
+            .min(self.get_commits_count()?);
+        let commits = self.commits.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(commits[start_index.min(end_index)..end_index]
+            .iter()
+            .enumerate()
+            .map(|(index, commit)| commit.to_summary(start_index + index))
+            .collect())
+    }
+
+    /// Rows of the commit graph for a range of the timeline.
+    pub fn get_commit_graph(
+        &self,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<Vec<GraphRow>, String> {
+        // Rows of the commit graph for the same range as `get_commit_summaries`
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        self.get_commits_count()?;
+        let graph = self.graph.lock().unwrap_or_else(PoisonError::into_inner);
+        let start_index = start_index.unwrap_or(0).min(graph.len());
+        let end_index = start_index
+            .saturating_add(count.unwrap_or(100))
+            .min(graph.len());
+        Ok(graph[start_index..end_index].to_vec())
+    }
+
+    /// Tags of the repository, oldest first.
+    pub fn get_tags(&self) -> Result<Vec<Tag>, String> {
+        // Tags found when the cache was prepared, oldest first
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        self.get_commits_count()?;
+        Ok(self
+            .tags
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone())
+    }
+
+    /// A commit with the files and directories of the requested folders, like "" and "src/".
+    /// Line counts read every file listed, so they are only counted with `with_line_counts`.
+    pub fn get_commit_details(
+        &self,
+        commit_id: &str,
+        requested_folders: Vec<&str>,
+        with_line_counts: bool,
+    ) -> Result<CommitFrame, String> {
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => {
+                let mut frame = walker::get_commit_details_with_cache(
+                    &repository,
+                    commit_id,
+                    true,
+                    Some(requested_folders),
+                    with_line_counts,
+                    &mut self
+                        .object_cache
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner),
+                )?;
+                // Frames of the cached timeline already know their decorations
+                if let Some(index) = self
+                    .commit_ids
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get(&frame.get_id())
+                {
+                    if let Some(commit) = self
+                        .commits
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .get(*index)
+                    {
+                        frame.set_decorations(commit.get_decorations().to_vec());
+                    }
+                }
+                let notes_refs = self
+                    .notes_refs
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                if !notes_refs.is_empty() {
+                    frame.read_notes(&repository, &notes_refs);
+                }
+                Ok(frame)
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// For each file of the requested folders, the commits of a range of the timeline where it
+    /// appeared or changed.
+    pub fn get_sizes_for_paths(
+        &self,
+        requested_folders: Vec<&str>,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<HashMap<String, HashMap<String, bool>>, String> {
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path_filter = PathFilter::for_requested_folders(&requested_folders)?;
+        let end_index = start_index
+            .unwrap_or(0)
+            .saturating_add(count.unwrap_or(100))
+            .min(self.get_commits_count()?);
+        let mut output: HashMap<String, HashMap<String, bool>> = HashMap::new();
+        let mut last_hash: HashMap<String, String> = HashMap::new();
+
+        // We scan the file hashes of each commit in parallel, then compare them in order
+        let commit_ids = self.get_commit_ids(start_index.unwrap_or(0), end_index);
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let scanned = scan_commits(&path, &commit_ids, |repository, object_cache, index| {
+            get_file_hashes_for_paths_in_commit(
+                repository,
+                commit_ids[index].as_str(),
+                &path_filter,
+                object_cache,
+            )
+        });
+        match scanned {
+            Ok(results) => {
+                for (commit_id, result) in commit_ids.iter().zip(results) {
+                    match result {
+                        Ok(vec_of_size_by_path) => {
+                            for size_by_path in vec_of_size_by_path {
+                                output
+                                    .entry(size_by_path.path.clone()) // Find existing entry for this file path
+                                    .and_modify(|existing| {
+                                        // There is existing entry for this file path
+                                        // We check if the file size in the last entry for this path is different from currnt size
+                                        if last_hash[&size_by_path.path] != size_by_path.hash {
+                                            // Sizes differ, so we insert new entry
+                                            existing.insert(commit_id.clone(), true);
+                                        }
+                                    })
+                                    .or_insert(HashMap::from([(commit_id.clone(), true)]));
+                                last_hash.insert(size_by_path.path, size_by_path.hash);
+                            }
+                        }
+                        Err(err) => {
+                            *self
+                                .last_error_message
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner) = Some(err);
+                        }
+                    }
+                }
+                Ok(output)
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// The files changed most often in the 40 commits from the given index, most changed first.
+    pub fn get_files_ordered_by_most_modifications(
+        &self,
+        start_index: Option<usize>,
+    ) -> Result<Vec<(String, usize)>, String> {
+        // Get a list of files that have the most number of modifications in the given range of commits
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let end_index = start_index
+            .unwrap_or(0)
+            .saturating_add(40)
+            .min(self.get_commits_count()?);
+        let mut all_files_with_count_of_modifications: HashMap<String, usize> = HashMap::new();
+        let mut last_hash: HashMap<String, String> = HashMap::new();
+
+        let commit_ids: Vec<String> = self
+            .get_commit_ids(start_index.unwrap_or(0), end_index)
+            .into_iter()
+            .step_by(4)
+            .collect();
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let scanned = scan_commits(&path, &commit_ids, |repository, object_cache, index| {
+            get_file_hashes_for_paths_in_commit(
+                repository,
+                commit_ids[index].as_str(),
+                &PathFilter::everything(),
+                object_cache,
+            )
+        });
+        match scanned {
+            Ok(results) => {
+                for result in results {
+                    match result {
+                        Ok(vec_of_size_by_path) => {
+                            for size_by_path in vec_of_size_by_path {
+                                all_files_with_count_of_modifications
+                                    .entry(size_by_path.path.clone()) // Find existing entry for this file path
+                                    .and_modify(|existing| {
+                                        // There is existing entry for this file path
+                                        // We check if the file size in the last entry for this path is different from currnt size
+                                        if last_hash[&size_by_path.path] != size_by_path.hash {
+                                            // Sizes differ, so we increment the entry
+                                            *existing += 1;
+                                        }
+                                    })
+                                    .or_insert(1);
+                                last_hash.insert(size_by_path.path, size_by_path.hash);
+                            }
+                        }
+                        Err(err) => {
+                            *self
+                                .last_error_message
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner) = Some(err);
+                        }
+                    }
+                }
+                let mut output: Vec<(String, usize)> =
+                    all_files_with_count_of_modifications.into_iter().collect();
+                // Filter the items that have 1 or less modifications
+                output.retain(|x| x.1 > 1);
+                // Sort the items by the number of modifications
+                output.sort_by_key(|x| x.1);
+                // Order the items by highest number of modifications first
+                output.reverse();
+                Ok(output[..min(output.len(), 16)].to_vec())
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// The commits of the timeline which added, modified or deleted the given file.
+    pub fn get_file_history(&self, file_path: &str) -> Result<Vec<CommitSummary>, String> {
+        // Get all the commits in our cached timeline where the given file was added, modified or
+        // deleted
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path_filter = PathFilter::new(&[file_path])?;
+        let commit_ids = self.get_commit_ids(0, self.get_commits_count()?);
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let scanned = scan_commits(&path, &commit_ids, |repository, object_cache, index| {
+            get_file_hashes_for_paths_in_commit(
+                repository,
+                commit_ids[index].as_str(),
+                &path_filter,
+                object_cache,
+            )
+        });
+        match scanned {
+            Ok(results) => {
+                let mut output: Vec<CommitSummary> = Vec::new();
+                let mut last_hash: Option<String> = None;
+                let commits = self.commits.lock().unwrap_or_else(PoisonError::into_inner);
+                for (index, result) in results.into_iter().enumerate() {
+                    match result {
+                        Ok(vec_of_size_by_path) => {
+                            let hash = vec_of_size_by_path
+                                .into_iter()
+                                .find(|x| x.path == file_path)
+                                .map(|x| x.hash);
+                            if hash != last_hash {
+                                output.push(commits[index].to_summary(index));
+                            }
+                            last_hash = hash;
+                        }
+                        Err(err) => {
+                            *self
+                                .last_error_message
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner) = Some(err);
+                        }
+                    }
+                }
+                Ok(output)
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// Commits and lines added and deleted for each file in a range of the timeline, files with
+    /// the most churn first.
+    pub fn get_file_churn(
+        &self,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<Vec<FileChurn>, String> {
+        // Get the number of commits and lines added and deleted for each file in the given range
+        // of commits, files with the most churn first
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let end_index = start_index
+            .unwrap_or(0)
+            .saturating_add(count.unwrap_or(100))
+            .min(self.get_commits_count()?);
+        let mut churn_by_path: HashMap<String, FileChurn> = HashMap::new();
+
+        let commit_ids = self.get_commit_ids(start_index.unwrap_or(0), end_index);
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let scanned = scan_commits(
+            &path,
+            &commit_ids,
+            |repository, _, index| match Oid::from_str(&commit_ids[index])
+                .and_then(|x| repository.find_commit(x))
+            {
+                Ok(commit) => get_churn_by_path(&get_diff_with_first_parent(repository, &commit)?),
+                Err(_) => Err("Could not parse the given revision specification".to_owned()),
+            },
+        );
+        match scanned {
+            Ok(results) => {
+                for result in results {
+                    match result {
+                        Ok(churn_in_commit) => {
+                            for churn in churn_in_commit {
+                                let existing =
+                                    churn_by_path
+                                        .entry(churn.path.clone())
+                                        .or_insert(FileChurn {
+                                            path: churn.path,
+                                            ..FileChurn::default()
+                                        });
+                                existing.commits += churn.commits;
+                                existing.additions += churn.additions;
+                                existing.deletions += churn.deletions;
+                            }
+                        }
+                        Err(err) => {
+                            *self
+                                .last_error_message
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner) = Some(err);
+                        }
+                    }
+                }
+                let mut output: Vec<FileChurn> = churn_by_path.into_values().collect();
+                output.sort_by(|a, b| {
+                    (b.additions + b.deletions)
+                        .cmp(&(a.additions + a.deletions))
+                        .then(a.path.cmp(&b.path))
+                });
+                Ok(output)
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// Index of the frame that was current at the given time, None before the first commit.
+    pub fn find_commit_at_time(&self, time: i64) -> Result<Option<usize>, String> {
+        // Index of the frame that was current at the given time: the last commit of the timeline
+        // made at or before it, not counting commits after a later one (like rebased commits).
+        // None before the first commit
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        self.get_commits_count()?;
+        let commit_times = self
+            .commit_times
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        Ok(commit_times.partition_point(|x| *x <= time).checked_sub(1))
+    }
+
+    /// The timeline grouped by hour, day, week or month.
+    pub fn get_time_buckets(
+        &self,
+        size: BucketSize,
+        include_empty: bool,
+    ) -> Result<Vec<TimeBucket>, String> {
+        // Group the whole timeline by hour, day, week or month, with the last frame of each
+        // bucket and the changes of all its commits
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        self.get_commits_count()?;
+        let times: Vec<i64> = self
+            .commits
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|x| x.get_time())
+            .collect();
+        // Check the number of buckets before scanning the changes of every commit
+        let ranges = match get_bucket_ranges(&times, size, include_empty) {
+            Ok(ranges) => ranges,
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                return Err(err);
+            }
+        };
+        let commit_ids = self.get_commit_ids(0, times.len());
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let scanned = scan_commits(
+            &path,
+            &commit_ids,
+            |repository, _, index| match Oid::from_str(&commit_ids[index])
+                .and_then(|x| repository.find_commit(x))
+            {
+                Ok(commit) => get_churn_by_path(&get_diff_with_first_parent(repository, &commit)?),
+                Err(_) => Err("Could not parse the given revision specification".to_owned()),
+            },
+        );
+        let churn_by_commit = match scanned {
+            Ok(results) => results.into_iter().collect::<Result<Vec<_>, String>>()?,
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                return Err(err);
+            }
+        };
+
+        let mut output: Vec<TimeBucket> = Vec::new();
+        for (start_time, range) in ranges {
+            let mut bucket = TimeBucket {
+                label: size.get_label(start_time),
+                start_time,
+                end_time: size.get_end(start_time),
+                first_commit_index: range.map(|(first, _)| first),
+                // Empty buckets are never first, there is always a bucket before them
+                last_commit_index: range.map_or_else(
+                    || output.last().map_or(0, |x| x.last_commit_index),
+                    |(_, last)| last,
+                ),
+                last_commit_id: String::new(),
+                commits: range.map_or(0, |(first, last)| last - first + 1),
+                additions: 0,
+                deletions: 0,
+                file_changes: Vec::new(),
+            };
+            bucket.last_commit_id = commit_ids[bucket.last_commit_index].clone();
+            let mut churn_by_path: HashMap<String, FileChurn> = HashMap::new();
+            if let Some((first, last)) = range {
+                for churn in churn_by_commit[first..=last].iter().flatten() {
+                    bucket.additions += churn.additions;
+                    bucket.deletions += churn.deletions;
+                    let existing = churn_by_path
+                        .entry(churn.path.clone())
+                        .or_insert(FileChurn {
+                            path: churn.path.clone(),
+                            ..FileChurn::default()
+                        });
+                    existing.commits += churn.commits;
+                    existing.additions += churn.additions;
+                    existing.deletions += churn.deletions;
+                }
+            }
+            bucket.file_changes = churn_by_path.into_values().collect();
+            bucket.file_changes.sort_by(|a, b| {
+                (b.additions + b.deletions)
+                    .cmp(&(a.additions + a.deletions))
+                    .then(a.path.cmp(&b.path))
+            });
+            output.push(bucket);
+        }
+        Ok(output)
+    }
+
+    fn get_commits_count(&self) -> Result<usize, String> {
+        // Number of commits in the cached timeline, which only exists once `prepare_cache` ran
+        // for the open repository
+        match *self
+            .commits_count
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            Some(commits_count) => Ok(commits_count),
+            None => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) =
+                    Some("Cache not prepared".to_owned());
+                Err("Cache not prepared".to_owned())
+            }
+        }
+    }
+
+    fn get_commit_ids(&self, start_index: usize, end_index: usize) -> Vec<String> {
+        // Copy the ids of a range of cached commits, so we do not hold the lock while scanning
+        let commits = self.commits.lock().unwrap_or_else(PoisonError::into_inner);
+        commits[start_index.min(end_index)..end_index]
+            .iter()
+            .map(|x| x.get_id())
+            .collect()
+    }
+
+    /// Files changed by a commit, with the given handling of merge commits.
+    pub fn get_commit_diff(
+        &self,
+        commit_id: &str,
+        mode: MergeDiffMode,
+    ) -> Result<CommitDiff, String> {
+        // Files changed by a commit, with the given handling of merges and their evil changes
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => {
+                match Oid::from_str(commit_id).and_then(|x| repository.find_commit(x)) {
+                    Ok(commit) => diff::get_commit_diff(&repository, &commit, mode),
+                    Err(_) => Err(format!("Commit {} does not exist", commit_id)),
+                }
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// The contents of a blob, as long as it is UTF-8 text.
+    pub fn read_file_contents(&self, object_id: &str) -> Result<String, String> {
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => walker::read_file_contents(&repository, object_id),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// Commits of the timeline which add or remove a string, like `git log -S`, or lines
+    /// matching a regular expression, like `git log -G`.
+    pub fn find_commits_changing_string(
+        &self,
+        pattern: &str,
+        is_regex: bool,
+        path_filters: Vec<&str>,
+    ) -> Result<Vec<PickaxeMatch>, String> {
+        // Find all the commits in our cached timeline which add or remove the given string (or
+        // lines matching the given regular expression), like `git log -S` or `git log -G`
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let pickaxe = Pickaxe::new(pattern, is_regex)?;
+        let path_filter = PathFilter::new(&path_filters)?;
+        // The ids are copied out, so other commands are not blocked while the history is searched
+        let commit_ids = self.get_commit_ids(0, self.get_commits_count()?);
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let scanned = scan_commits(&path, &commit_ids, |repository, _, index| {
+            get_paths_matching_pickaxe(
+                repository,
+                commit_ids[index].as_str(),
+                &pickaxe,
+                &path_filter,
+            )
+        });
+        match scanned {
+            Ok(results) => {
+                let mut output: Vec<PickaxeMatch> = Vec::new();
+                for (index, result) in results.into_iter().enumerate() {
+                    match result {
+                        Ok(paths) => {
+                            if !paths.is_empty() {
+                                output.push(PickaxeMatch {
+                                    commit_id: commit_ids[index].clone(),
+                                    commit_index: index,
+                                    paths,
+                                });
+                            }
+                        }
+                        Err(err) => {
+                            *self
+                                .last_error_message
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner) = Some(err);
+                        }
+                    }
+                }
+                Ok(output)
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// Lines matching a string or regular expression in the files of a commit, like `git grep`.
+    pub fn grep_at_commit(
+        &self,
+        commit_id: &str,
+        pattern: &str,
+        is_regex: bool,
+        path_globs: Vec<&str>,
+    ) -> Result<Vec<GrepMatch>, String> {
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => search::grep_at_commit(
+                &repository,
+                commit_id,
+                pattern,
+                is_regex,
+                path_globs,
+                &mut self
+                    .object_cache
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner),
+            ),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// Functions, types and other symbols of the supported source files of a commit.
+    pub fn get_symbols_at_commit(
+        &self,
+        commit_id: &str,
+        path_filters: Vec<&str>,
+    ) -> Result<Vec<FileSymbols>, String> {
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => self
+                .symbol_index
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get_symbols_at_commit(
+                    &repository,
+                    commit_id,
+                    &PathFilter::new(&path_filters)?,
+                    &mut self
+                        .object_cache
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner),
+                ),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// When a symbol was introduced, modified, moved, renamed or deleted across the timeline.
+    pub fn get_symbol_timeline(&self, name: &str) -> Result<Vec<SymbolEvent>, String> {
+        // Follow the symbol with the given name across the entire cached timeline
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let commit_ids: Vec<String> = self
+            .commits
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|x| x.get_id())
+            .collect();
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => self
+                .symbol_index
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get_symbol_timeline(&repository, &commit_ids, name),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// Files, code, comment and blank lines by language for each frame of a range.
+    pub fn get_language_stats(
+        &self,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<Vec<FrameLanguageStats>, String> {
+        // Get the language breakdown (files, code, comment and blank lines) of each frame in range
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let end_index = start_index
+            .unwrap_or(0)
+            .saturating_add(count.unwrap_or(100))
+            .min(self.get_commits_count()?);
+        let commit_ids: Vec<String> = self
+            .commits
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|x| x.get_id())
+            .collect();
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => self
+                .language_stats
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get_language_stats(
+                    &repository,
+                    &commit_ids,
+                    start_index.unwrap_or(0),
+                    end_index,
+                ),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// Changes of a range of the timeline in Gource's custom log format.
+    pub fn get_gource_log(
+        &self,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<String, String> {
+        // Get the changes of the given range of commits in Gource's custom log format, the whole
+        // timeline if no count is given
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let commits_count = self.get_commits_count()?;
+        let end_index = count.map_or(commits_count, |count| {
+            start_index
+                .unwrap_or(0)
+                .saturating_add(count)
+                .min(commits_count)
+        });
+        let commit_ids = self.get_commit_ids(start_index.unwrap_or(0), end_index);
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let scanned = scan_commits(
+            &path,
+            &commit_ids,
+            |repository, _, index| match Oid::from_str(&commit_ids[index])
+                .and_then(|x| repository.find_commit(x))
+            {
+                Ok(commit) => get_gource_lines(repository, &commit),
+                Err(_) => Err("Could not parse the given revision specification".to_owned()),
+            },
+        );
+        match scanned {
+            Ok(results) => {
+                // Gource reads the log in order, so the lines stay in the order of the timeline
+                let mut output = String::new();
+                for result in results {
+                    for line in result? {
+                        output.push_str(&line);
+                        output.push('\n');
+                    }
+                }
+                Ok(output)
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// Write the Gource log of a range of the timeline to a file, returns the number of lines.
+    pub fn export_gource_log(
+        &self,
+        file_path: &str,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<usize, String> {
+        // Write the Gource log of the given range of commits to a file, returns the number of lines
+        let log = self.get_gource_log(start_index, count)?;
+        match fs::write(file_path, &log) {
+            Ok(_) => Ok(log.lines().count()),
+            Err(err) => {
+                let message = format!("Could not write {}: {}", file_path, err);
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(message.clone());
+                Err(message)
+            }
+        }
+    }
+
+    /// Statistics of a range of the timeline: commits, file changes, authors and frame sizes.
+    pub fn get_report(
+        &self,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<Report, String> {
+        // Get the statistics of the given range of commits as tables, the whole timeline if no
+        // count is given
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let commits_count = self.get_commits_count()?;
+        let start_index = start_index.unwrap_or(0).min(commits_count);
+        let end_index = count.map_or(commits_count, |count| {
+            start_index.saturating_add(count).min(commits_count)
+        });
+        let commits: Vec<CommitSummary> =
+            self.commits.lock().unwrap_or_else(PoisonError::into_inner)[start_index..end_index]
+                .iter()
+                .enumerate()
+                .map(|(index, commit)| commit.to_summary(start_index + index))
+                .collect();
+
+        // The changes and the size of the tree of each commit are scanned in parallel
+        let commit_ids: Vec<String> = commits.iter().map(|x| x.commit_id.clone()).collect();
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let scanned =
+            scan_commits(
+                &path,
+                &commit_ids,
+                |repository, object_cache, index| match Oid::from_str(&commit_ids[index])
+                    .and_then(|x| repository.find_commit(x))
+                {
+                    Ok(commit) => Ok((
+                        get_churn_by_path(&get_diff_with_first_parent(repository, &commit)?)?,
+                        get_frame_size(repository, &commit, start_index + index, object_cache)?,
+                    )),
+                    Err(_) => Err("Could not parse the given revision specification".to_owned()),
+                },
+            );
+        let results = match scanned {
+            Ok(results) => results,
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                return Err(err);
+            }
+        };
+
+        let mut churn_by_path: HashMap<String, FileChurn> = HashMap::new();
+        let mut activity_by_author: HashMap<String, AuthorActivity> = HashMap::new();
+        let mut frame_sizes = Vec::new();
+        for (commit, result) in commits.iter().zip(results) {
+            let (churn_in_commit, frame_size) = result?;
+            let activity =
+                activity_by_author
+                    .entry(commit.author.clone())
+                    .or_insert(AuthorActivity {
+                        author: commit.author.clone(),
+                        commits: 0,
+                        files_changed: 0,
+                        additions: 0,
+                        deletions: 0,
+                        first_commit_time: commit.time,
+                        last_commit_time: commit.time,
+                    });
+            activity.commits += 1;
+            activity.first_commit_time = activity.first_commit_time.min(commit.time);
+            activity.last_commit_time = activity.last_commit_time.max(commit.time);
+            for churn in churn_in_commit {
+                activity.files_changed += 1;
+                activity.additions += churn.additions;
+                activity.deletions += churn.deletions;
+                let existing = churn_by_path
+                    .entry(churn.path.clone())
+                    .or_insert(FileChurn {
+                        path: churn.path,
+                        ..FileChurn::default()
+                    });
+                existing.commits += churn.commits;
+                existing.additions += churn.additions;
+                existing.deletions += churn.deletions;
+            }
+            frame_sizes.push(frame_size);
+        }
+
+        // Files changed most often first, authors with the most commits first
+        let mut file_changes: Vec<FileChurn> = churn_by_path.into_values().collect();
+        file_changes.sort_by(|a, b| b.commits.cmp(&a.commits).then(a.path.cmp(&b.path)));
+        let mut authors: Vec<AuthorActivity> = activity_by_author.into_values().collect();
+        authors.sort_by(|a, b| b.commits.cmp(&a.commits).then(a.author.cmp(&b.author)));
+        Ok(Report {
+            start_index,
+            end_index,
+            commits,
+            file_changes,
+            authors,
+            frame_sizes,
+        })
+    }
+
+    /// Write the report of a range of the timeline as CSV and JSON files in a folder, returns the
+    /// paths of the files written.
+    pub fn export_report(
+        &self,
+        folder_path: &str,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<Vec<String>, String> {
+        // Write the report of the given range of commits as CSV and JSON files in a folder,
+        // returns the paths of the files written
+        let report = self.get_report(start_index, count)?;
+        match report.write_to_folder(Path::new(folder_path)) {
+            Ok(paths) => Ok(paths
+                .iter()
+                .map(|x| x.to_string_lossy().into_owned())
+                .collect()),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// The report of a range of the timeline as a self-contained HTML page.
+    pub fn get_html_report(
+        &self,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<String, String> {
+        // Render the report of the given range of commits as a self-contained HTML page, the
+        // whole timeline if no count is given
+        let report = self.get_report(start_index, count)?;
+
+        // The directory chart needs far fewer points than there are commits, so we sample up to
+        // 60 frames evenly, always including the last one
+        let range_length = report.end_index - report.start_index;
+        let mut sampled: Vec<usize> = (0..60.min(range_length))
+            .map(|x| report.start_index + x * range_length / 60.min(range_length))
+            .collect();
+        if range_length > 0 && sampled.last() != Some(&(report.end_index - 1)) {
+            sampled.push(report.end_index - 1);
+        }
+        let commit_ids: Vec<String> = sampled
+            .iter()
+            .map(|x| report.commits[x - report.start_index].commit_id.clone())
+            .collect();
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let scanned =
+            scan_commits(
+                &path,
+                &commit_ids,
+                |repository, object_cache, index| match Oid::from_str(&commit_ids[index])
+                    .and_then(|x| repository.find_commit(x))
+                {
+                    Ok(commit) => {
+                        get_directory_sizes(repository, &commit, sampled[index], object_cache)
+                    }
+                    Err(_) => Err("Could not parse the given revision specification".to_owned()),
+                },
+            );
+        let directory_timeline = match scanned {
+            Ok(results) => results.into_iter().collect::<Result<Vec<_>, String>>()?,
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                return Err(err);
+            }
+        };
+
+        let releases = self.get_tags()?;
+        // The name of the folder of the repository is the title of the report
+        let title = path
+            .canonicalize()
+            .unwrap_or(path.clone())
+            .file_name()
+            .map_or("Repository".to_owned(), |x| {
+                x.to_string_lossy().into_owned()
+            });
+        Ok(render_html_report(&HtmlReport {
+            title,
+            report: &report,
+            directory_timeline,
+            releases,
+        }))
+    }
+
+    /// Write the HTML report of a range of the timeline to a file, returns its path.
+    pub fn export_html_report(
+        &self,
+        file_path: &str,
+        start_index: Option<usize>,
+        count: Option<usize>,
+    ) -> Result<String, String> {
+        // Write the HTML report of the given range of commits to a file, returns its path
+        let html = self.get_html_report(start_index, count)?;
+        match fs::write(file_path, html) {
+            Ok(_) => Ok(file_path.to_owned()),
+            Err(err) => {
+                let message = format!("Could not write {}: {}", file_path, err);
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(message.clone());
+                Err(message)
+            }
+        }
+    }
+
+    /// Tours saved in the tours folder of the repository.
+    pub fn list_tours(&self) -> Result<Vec<TourFile>, String> {
+        // List the tours saved in the tours folder of the repository
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => tours::list_tours(&repository),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// Start recording a new tour, saved to the given file or to the tours folder.
+    pub fn create_tour(&self, name: &str, file_path: Option<&str>) -> Result<ActiveTour, String> {
+        // Start recording a new tour, saved to the given file or to the tours folder of the
+        // repository. An existing tour is never overwritten
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let file_path = match (file_path, Repository::open(path)) {
+            (Some(file_path), _) => PathBuf::from(file_path),
+            (None, Ok(repository)) => tours::get_default_tour_path(&repository, name),
+            (None, Err(err)) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                return Err(err.message().to_string());
+            }
+        };
+        if file_path.exists() {
+            let message = format!("{} already exists", file_path.display());
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = Some(message.clone());
+            return Err(message);
+        }
+        let tour = Tour::new(name);
+        tour.save(&file_path)?;
+        let active_tour = ActiveTour {
+            file_path: file_path.to_string_lossy().into_owned(),
+            tour,
+            current_step: None,
+        };
+        *self.tour.lock().unwrap_or_else(PoisonError::into_inner) = Some(active_tour.clone());
+        Ok(active_tour)
+    }
+
+    /// Load a tour to play or edit it, it starts before its first step.
+    pub fn load_tour(&self, file_path: &str) -> Result<ActiveTour, String> {
+        // Load a tour to play or edit it, it starts before its first step
+        let tour = match Tour::load(Path::new(file_path)) {
+            Ok(tour) => tour,
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                return Err(err);
+            }
+        };
+        let active_tour = ActiveTour {
+            file_path: file_path.to_owned(),
+            tour,
+            current_step: None,
+        };
+        *self.tour.lock().unwrap_or_else(PoisonError::into_inner) = Some(active_tour.clone());
+        Ok(active_tour)
+    }
+
+    /// The tour being played or edited, if any.
+    pub fn get_active_tour(&self) -> Option<ActiveTour> {
+        self.tour
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Rename the active tour and change its description.
+    pub fn update_tour(&self, name: &str, description: &str) -> Result<ActiveTour, String> {
+        self.edit_tour(|active_tour| {
+            active_tour.tour.name = name.to_owned();
+            active_tour.tour.description = description.to_owned();
+            Ok(())
+        })
+    }
+
+    /// Insert a step in the active tour at the given index, or after the last step.
+    pub fn add_tour_step(
+        &self,
+        step: TourStep,
+        step_index: Option<usize>,
+    ) -> Result<ActiveTour, String> {
+        // Insert a step at the given index, or after the last step, and move to it
+        self.edit_tour(|active_tour| {
+            let steps = &mut active_tour.tour.steps;
+            let step_index = step_index.unwrap_or(steps.len());
+            if step_index > steps.len() {
+                return Err(format!("The tour has only {} steps", steps.len()));
+            }
+            steps.insert(step_index, step);
+            active_tour.current_step = Some(step_index);
+            Ok(())
+        })
+    }
+
+    /// Replace a step of the active tour.
+    pub fn update_tour_step(
+        &self,
+        step_index: usize,
+        step: TourStep,
+    ) -> Result<ActiveTour, String> {
+        self.edit_tour(
+            |active_tour| match active_tour.tour.steps.get_mut(step_index) {
+                Some(existing) => {
+                    *existing = step;
+                    Ok(())
+                }
+                None => Err(format!("Step {} is not in the tour", step_index)),
+            },
+        )
+    }
+
+    /// Remove a step of the active tour.
+    pub fn remove_tour_step(&self, step_index: usize) -> Result<ActiveTour, String> {
+        self.edit_tour(|active_tour| {
+            if step_index >= active_tour.tour.steps.len() {
+                return Err(format!("Step {} is not in the tour", step_index));
+            }
+            active_tour.tour.steps.remove(step_index);
+            // Stay at the same position, or at the new last step
+            let steps_count = active_tour.tour.steps.len();
+            active_tour.current_step = active_tour
+                .current_step
+                .filter(|_| steps_count > 0)
+                .map(|x| x.min(steps_count - 1));
+            Ok(())
+        })
+    }
+
+    /// Steps of the active tour pointing at commits, folders, files or lines which do not exist.
+    pub fn validate_tour(&self) -> Result<Vec<TourProblem>, String> {
+        // Find the steps of the active tour which point at commits, folders, files or lines that
+        // do not exist in the repository
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let tour = match self
+            .tour
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+        {
+            Some(active_tour) => active_tour.tour.clone(),
+            None => return Err("No tour is loaded".to_owned()),
+        };
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => Ok(tours::validate_tour(&repository, &tour)),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// Move the active tour to a step, with the frame of its commit in the timeline.
+    pub fn go_to_tour_step(&self, step_index: usize) -> Result<TourStepView, String> {
+        // Move the active tour to a step, with the frame of its commit in our timeline
+        let mut tour = self.tour.lock().unwrap_or_else(PoisonError::into_inner);
+        let active_tour = match tour.as_mut() {
+            Some(active_tour) => active_tour,
+            None => return Err("No tour is loaded".to_owned()),
+        };
+        let step = match active_tour.tour.steps.get(step_index) {
+            Some(step) => step.clone(),
+            None => return Err(format!("Step {} is not in the tour", step_index)),
+        };
+        active_tour.current_step = Some(step_index);
+        Ok(TourStepView {
+            step_index,
+            steps_count: active_tour.tour.steps.len(),
+            commit_index: self
+                .commit_ids
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(&step.commit_id)
+                .copied(),
+            step,
+        })
+    }
+
+    /// Move the active tour to its next step, the first one if it has not started.
+    pub fn next_tour_step(&self) -> Result<TourStepView, String> {
+        // The first step if the tour has not started yet
+        let step_index = match self
+            .tour
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+        {
+            Some(active_tour) => active_tour.current_step.map_or(0, |x| x + 1),
+            None => return Err("No tour is loaded".to_owned()),
+        };
+        self.go_to_tour_step(step_index)
+    }
+
+    /// Move the active tour to its previous step.
+    pub fn previous_tour_step(&self) -> Result<TourStepView, String> {
+        let step_index = match self
+            .tour
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+        {
+            Some(active_tour) => match active_tour.current_step {
+                Some(step_index) if step_index > 0 => step_index - 1,
+                _ => return Err("This is the first step of the tour".to_owned()),
+            },
+            None => return Err("No tour is loaded".to_owned()),
+        };
+        self.go_to_tour_step(step_index)
+    }
+
+    fn edit_tour<F>(&self, edit: F) -> Result<ActiveTour, String>
+    where
+        F: FnOnce(&mut ActiveTour) -> Result<(), String>,
+    {
+        // Apply a change to the active tour and save it to its file, the active tour is left
+        // unchanged if either fails
+        let mut tour = self.tour.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut edited = match tour.as_ref() {
+            Some(active_tour) => active_tour.clone(),
+            None => return Err("No tour is loaded".to_owned()),
+        };
+        let result = edit(&mut edited).and_then(|_| edited.tour.save(Path::new(&edited.file_path)));
+        match result {
+            Ok(_) => {
+                *tour = Some(edited.clone());
+                Ok(edited)
+            }
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// The notes refs read into commit frames.
+    pub fn get_notes_refs(&self) -> Vec<String> {
+        self.notes_refs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Choose the notes refs read into commit frames, like "commits" for refs/notes/commits. The
+    /// choice is saved in the Git config of the repository and loaded again by `open`.
+    pub fn set_notes_refs(&self, notes_refs: Vec<&str>) -> Result<Vec<String>, String> {
+        // Choose the notes refs that are read into commit frames, short names like "commits" are
+        // expanded to refs/notes/commits. Refs do not have to exist yet
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let mut full_names: Vec<String> = Vec::new();
+        for notes_ref in notes_refs {
+            let full_name = notes::get_full_notes_ref(notes_ref)?;
+            if !full_names.contains(&full_name) {
+                full_names.push(full_name);
+            }
+        }
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let result = match Repository::open(path) {
+            Ok(repository) => notes::write_configured_notes_refs(&repository, &full_names),
+            Err(err) => Err(err.message().to_string()),
+        };
+        if let Err(err) = result {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+            return Err(err);
+        }
+        *self
+            .notes_refs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = full_names.clone();
+        Ok(full_names)
+    }
+
+    /// Notes refs present in the repository.
+    pub fn list_notes_refs(&self) -> Result<Vec<String>, String> {
+        // Notes refs present in the repository, to choose from
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => Ok(notes::list_notes_refs(&repository)),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// Add or replace the note of a commit, an empty message removes it.
+    pub fn set_commit_note(
+        &self,
+        commit_id: &str,
+        notes_ref: Option<&str>,
+        message: &str,
+    ) -> Result<Vec<CommitNote>, String> {
+        // Add or replace the note of a commit, an empty message removes it
+        // Without a notes ref, the first configured one is used, else refs/notes/commits like Git
+        // Returns the notes of the commit in the configured refs and the one written to
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let mut notes_refs = self
+            .notes_refs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let notes_ref = match notes_ref {
+            Some(notes_ref) => notes::get_full_notes_ref(notes_ref)?,
+            None => notes_refs
+                .first()
+                .cloned()
+                .unwrap_or_else(|| DEFAULT_NOTES_REF.to_owned()),
+        };
+        if !notes_refs.contains(&notes_ref) {
+            notes_refs.push(notes_ref.clone());
+        }
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let repository = match Repository::open(path) {
+            Ok(repository) => repository,
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                return Err(err.message().to_string());
+            }
+        };
+        if let Err(err) = notes::write_note(&repository, &notes_ref, commit_id, message) {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+            return Err(err);
+        }
+        match Oid::from_str(commit_id) {
+            Ok(commit_id) => Ok(notes::get_commit_notes(&repository, commit_id, &notes_refs)),
+            Err(err) => Err(err.message().to_string()),
+        }
+    }
+
+    /// All annotations of the repository, local and shared, oldest first.
+    pub fn list_annotations(&self) -> Result<Vec<Annotation>, String> {
+        // All annotations of the repository, local and shared, oldest first
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        match Repository::open(path) {
+            Ok(repository) => annotations::load_annotations(&repository),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                Err(err.message().to_string())
+            }
+        }
+    }
+
+    /// Annotations visible at a frame of the timeline.
+    pub fn get_annotations_at_commit(
+        &self,
+        commit_index: usize,
+    ) -> Result<Vec<Annotation>, String> {
+        // Annotations visible at a frame of the timeline: those of its commit, of paths at its
+        // commit and of ranges of commits around it
+        let all_annotations = self.list_annotations()?;
+        let commit_ids = self
+            .commit_ids
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        Ok(all_annotations
+            .into_iter()
+            .filter(|x| x.target.is_visible_at(commit_index, &commit_ids))
+            .collect())
+    }
+
+    /// Annotate a commit, a path at a commit or a range of commits, shared as a Git note or
+    /// kept local to this clone.
+    pub fn add_annotation(
+        &self,
+        target: AnnotationTarget,
+        text: &str,
+        shared: bool,
+    ) -> Result<Annotation, String> {
+        if self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none()
+        {
+            *self
+                .last_error_message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) =
+                Some("Repository path is not set".to_owned());
+            return Err("Repository path is not set".to_owned());
+        }
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let repository = match Repository::open(path) {
+            Ok(repository) => repository,
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.message().to_string());
+                return Err(err.message().to_string());
+            }
+        };
+        // The commits (and path) of the target have to exist
+        let (commit_ids, target_path) = match &target {
+            AnnotationTarget::Commit { commit_id } => (vec![commit_id], None),
+            AnnotationTarget::Path { commit_id, path } => (vec![commit_id], Some(path)),
+            AnnotationTarget::Range {
+                from_commit_id,
+                to_commit_id,
+            } => (vec![from_commit_id, to_commit_id], None),
+        };
+        for commit_id in commit_ids {
+            let commit = match Oid::from_str(commit_id).and_then(|x| repository.find_commit(x)) {
+                Ok(commit) => commit,
+                Err(_) => return Err(format!("Commit {} does not exist", commit_id)),
+            };
+            if let Some(target_path) = target_path {
+                let exists = commit
+                    .tree()
+                    .and_then(|x| x.get_path(Path::new(target_path.trim_end_matches('/'))))
+                    .is_ok();
+                if !exists {
+                    return Err(format!("{} does not exist at this commit", target_path));
+                }
+            }
+        }
+
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_secs() as i64);
+        let annotation = Annotation {
+            id: new_annotation_id(&target, text, created_at),
+            target,
+            text: text.to_owned(),
+            created_at,
+            shared,
+        };
+        let mut all_annotations = annotations::load_annotations(&repository)?;
+        all_annotations.push(annotation.clone());
+        let changed_anchors: Vec<String> = match shared {
+            true => vec![annotation.target.get_anchor_commit_id().to_owned()],
+            false => vec![],
+        };
+        match annotations::save_annotations(&repository, &all_annotations, &changed_anchors) {
+            Ok(_) => Ok(annotation),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// Change the text of an annotation, or whether it is shared as a Git note.
+    pub fn update_annotation(
+        &self,
+        id: &str,
+        text: &str,
+        shared: bool,
+    ) -> Result<Annotation, String> {
+        // Change the text of an annotation, or whether it is shared as a Git note
+        self.edit_annotations(id, |all_annotations, index| {
+            let was_shared = all_annotations[index].shared;
+            all_annotations[index].text = text.to_owned();
+            all_annotations[index].shared = shared;
+            Some(all_annotations[index].clone()).filter(|_| was_shared || shared)
+        })
+    }
+
+    /// Remove an annotation, and its Git note if it was shared.
+    pub fn remove_annotation(&self, id: &str) -> Result<Annotation, String> {
+        self.edit_annotations(id, |all_annotations, index| {
+            let annotation = all_annotations.remove(index);
+            Some(annotation).filter(|x| x.shared)
+        })
+    }
+
+    fn edit_annotations<F>(&self, id: &str, edit: F) -> Result<Annotation, String>
+    where
+        F: FnOnce(&mut Vec<Annotation>, usize) -> Option<Annotation>,
+    {
+        // Apply a change to the annotation with the given id and save all annotations
+        // The change returns the annotation if its Git note has to be rewritten
+        let mut all_annotations = self.list_annotations()?;
+        let index = match all_annotations.iter().position(|x| x.id == id) {
+            Some(index) => index,
+            None => return Err(format!("Annotation {} does not exist", id)),
+        };
+        let before = all_annotations[index].clone();
+        let changed_anchors: Vec<String> = edit(&mut all_annotations, index)
+            .iter()
+            .map(|x| x.target.get_anchor_commit_id().to_owned())
+            .collect();
+        // Removed annotations are returned as they were
+        let annotation = all_annotations
+            .iter()
+            .find(|x| x.id == id)
+            .cloned()
+            .unwrap_or(before);
+
+        let path = self
+            .repository_path
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap();
+        let result = match Repository::open(path) {
+            Ok(repository) => {
+                annotations::save_annotations(&repository, &all_annotations, &changed_anchors)
+            }
+            Err(err) => Err(err.message().to_string()),
+        };
+        match result {
+            Ok(_) => Ok(annotation),
+            Err(err) => {
+                *self
+                    .last_error_message
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn state_is_usable_after_a_panic_holding_its_locks() {
+        let folder = tempfile::TempDir::new().unwrap();
+        let repository = Repository::init(folder.path()).unwrap();
+        let signature = git2::Signature::now("Fixture", "fixture@example.com").unwrap();
+        let tree_id = repository.treebuilder(None).unwrap().write().unwrap();
+        let tree = repository.find_tree(tree_id).unwrap();
+        repository
+            .commit(Some("HEAD"), &signature, &signature, "First", &tree, &[])
+            .unwrap();
+        let state = GitplayState::new();
+        state.open(folder.path().to_path_buf()).unwrap();
+        state.prepare_cache().unwrap();
+
+        // Like a command of the server panicking halfway, on one of its worker threads
+        thread::scope(|scope| {
+            let result = scope
+                .spawn(|| {
+                    let _repository_path = state.repository_path.lock().unwrap();
+                    let _commits = state.commits.lock().unwrap();
+                    panic!("Command failed");
+                })
+                .join();
+            assert!(result.is_err());
+        });
+        assert!(state.commits.is_poisoned());
+
+        assert_eq!(state.get_commit_summaries(None, None).unwrap().len(), 1);
+        assert_eq!(state.prepare_cache().unwrap().0, 1);
+        state.open(folder.path().to_path_buf()).unwrap();
+        assert!(state.get_commit_summaries(None, None).is_err());
+    }
+}
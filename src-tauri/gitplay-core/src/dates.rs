@@ -0,0 +1,43 @@
+/*
+Dates in UTC from seconds since the Unix epoch, without a date library. Days are converted to and
+from civil dates with Howard Hinnant's algorithms, which work for any date in the proleptic
+Gregorian calendar.
+ */
+
+pub(crate) const SECONDS_PER_DAY: i64 = 86400;
+
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    // Year, month (1 to 12) and day (1 to 31) of a number of days since 1970-01-01
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Number of days since 1970-01-01 of a civil date, the inverse of `civil_from_days`
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+pub(crate) fn format_date(time: i64) -> String {
+    // Like 2023-07-14
+    let (year, month, day) = civil_from_days(time.div_euclid(SECONDS_PER_DAY));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
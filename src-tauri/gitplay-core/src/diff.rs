@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use git2::{Commit, Delta, Diff, ObjectType, Oid, Patch, Repository, TreeWalkMode, TreeWalkResult};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FileChurn {
+    pub path: String,
+    pub commits: usize,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+pub fn get_diff_with_first_parent<'a>(
+    repository: &'a Repository,
+    commit: &Commit,
+) -> Result<Diff<'a>, String> {
+    // Get the changes introduced by a commit, compared to its first parent
+    // The very first commit of a repository has no parent, so we compare it with an empty tree
+    let old_tree = match commit.parent(0) {
+        Ok(parent) => match parent.tree() {
+            Ok(tree) => Some(tree),
+            Err(_) => return Err("Could not extract tree of parent commit".to_owned()),
+        },
+        Err(_) => None,
+    };
+    let new_tree = match commit.tree() {
+        Ok(tree) => tree,
+        Err(_) => return Err("Could not extract tree of commit".to_owned()),
+    };
+
+    match repository.diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None) {
+        Ok(diff) => Ok(diff),
+        Err(err) => Err(format!("Could not diff commit: {}", err.message())),
+    }
+}
+
+pub fn get_churn_by_path(diff: &Diff) -> Result<Vec<FileChurn>, String> {
+    // Count the lines added and deleted in each file of the diff, binary files have no lines
+    let mut output: Vec<FileChurn> = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let path = match delta.new_file().path().or(delta.old_file().path()) {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        let (additions, deletions) = match Patch::from_diff(diff, index) {
+            Ok(Some(patch)) => match patch.line_stats() {
+                Ok((_, additions, deletions)) => (additions, deletions),
+                Err(err) => return Err(format!("Could not read diff: {}", err.message())),
+            },
+            Ok(None) => (0, 0),
+            Err(err) => return Err(format!("Could not read diff: {}", err.message())),
+        };
+        output.push(FileChurn {
+            path,
+            commits: 1,
+            additions,
+            deletions,
+        });
+    }
+    Ok(output)
+}
+
+pub fn get_changes_by_path(diff: &Diff) -> Vec<FileChange> {
+    // Whether each file of the diff was added, modified or deleted
+    // Renames are not detected, so a moved file is deleted at its old path and added at the new one
+    let mut output: Vec<FileChange> = Vec::new();
+    for delta in diff.deltas() {
+        let path = match delta.new_file().path().or(delta.old_file().path()) {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        let kind = match delta.status() {
+            Delta::Added | Delta::Copied => ChangeKind::Added,
+            Delta::Deleted => ChangeKind::Deleted,
+            _ => ChangeKind::Modified,
+        };
+        output.push(FileChange { path, kind });
+    }
+    output
+}
+
+/*
+A merge commit has no single "what changed": it can be compared with its first parent (the branch
+that was merged into, what `git log -p --first-parent` shows), reduced to the files that differ
+from every parent (what `git diff --cc` focuses on: where the merge had to decide) or compared
+with each parent separately. Commits with a single parent (or none) give the same diff in every
+mode.
+ */
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeDiffMode {
+    #[default]
+    FirstParent,
+    Combined,
+    PerParent,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ParentDiff {
+    // None for the first commit of a repository and for the combined diff of a merge
+    pub parent_id: Option<String>,
+    pub files: Vec<FileDiff>,
+}
+
+/*
+EvilChange is a change of a merge that comes from neither side. For a merge of two commits, the
+merge is redone by libgit2: a file which merged cleanly but is different in the merge commit was
+changed by hand. For files with conflicts (and for merges of more than two commits), the lines of
+the merge which are in none of the parents were written during the merge.
+ */
+#[derive(Clone, Debug, Serialize)]
+pub struct EvilChange {
+    pub path: String,
+    pub conflicted: bool,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CommitDiff {
+    pub commit_id: String,
+    pub is_merge: bool,
+    pub mode: MergeDiffMode,
+    pub parents: Vec<ParentDiff>,
+    // Only looked for in merges
+    pub evil_changes: Vec<EvilChange>,
+}
+
+pub fn get_commit_diff(
+    repository: &Repository,
+    commit: &Commit,
+    mode: MergeDiffMode,
+) -> Result<CommitDiff, String> {
+    let parents: Vec<Commit> = commit.parents().collect();
+    let is_merge = parents.len() > 1;
+    let diff_with = |parent: Option<&Commit>| -> Result<ParentDiff, String> {
+        let old_tree = match parent.map(|x| x.tree()) {
+            Some(Ok(tree)) => Some(tree),
+            Some(Err(_)) => return Err("Could not extract tree of parent commit".to_owned()),
+            None => None,
+        };
+        let new_tree = commit
+            .tree()
+            .map_err(|_| "Could not extract tree of commit".to_owned())?;
+        let diff = repository
+            .diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None)
+            .map_err(|err| format!("Could not diff commit: {}", err.message()))?;
+        Ok(ParentDiff {
+            parent_id: parent.map(|x| x.id().to_string()),
+            files: get_file_diffs(&diff)?,
+        })
+    };
+
+    let parent_diffs = match (mode, is_merge) {
+        (MergeDiffMode::PerParent, true) => parents
+            .iter()
+            .map(|x| diff_with(Some(x)))
+            .collect::<Result<Vec<_>, String>>()?,
+        (MergeDiffMode::Combined, true) => {
+            // Files changed compared to every parent, with the lines changed since the first one
+            let per_parent = parents
+                .iter()
+                .map(|x| diff_with(Some(x)))
+                .collect::<Result<Vec<_>, String>>()?;
+            let files = per_parent[0]
+                .files
+                .iter()
+                .filter(|file| {
+                    per_parent[1..]
+                        .iter()
+                        .all(|x| x.files.iter().any(|y| y.path == file.path))
+                })
+                .cloned()
+                .collect();
+            vec![ParentDiff {
+                parent_id: None,
+                files,
+            }]
+        }
+        _ => vec![diff_with(parents.first())?],
+    };
+    let evil_changes = match is_merge {
+        true => get_evil_changes(repository, commit, &parents)?,
+        false => Vec::new(),
+    };
+    Ok(CommitDiff {
+        commit_id: commit.id().to_string(),
+        is_merge,
+        mode,
+        parents: parent_diffs,
+        evil_changes,
+    })
+}
+
+fn get_file_diffs(diff: &Diff) -> Result<Vec<FileDiff>, String> {
+    let changes = get_changes_by_path(diff);
+    let churn = get_churn_by_path(diff)?;
+    Ok(changes
+        .into_iter()
+        .zip(churn)
+        .map(|(change, churn)| FileDiff {
+            path: change.path,
+            kind: change.kind,
+            additions: churn.additions,
+            deletions: churn.deletions,
+        })
+        .collect())
+}
+
+fn get_evil_changes(
+    repository: &Repository,
+    commit: &Commit,
+    parents: &[Commit],
+) -> Result<Vec<EvilChange>, String> {
+    let merged_files = get_blob_ids_by_path(commit)?;
+    let parent_files = parents
+        .iter()
+        .map(get_blob_ids_by_path)
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // Redo the merge of two parents, None when it can not be redone (like unrelated histories)
+    // or for octopus merges
+    let redone = match parents {
+        [ours, theirs] => repository.merge_commits(ours, theirs, None).ok(),
+        _ => None,
+    };
+    let mut redone_files: BTreeMap<String, Oid> = BTreeMap::new();
+    let mut conflicted: BTreeSet<String> = BTreeSet::new();
+    if let Some(index) = &redone {
+        for entry in index.iter() {
+            // Submodules are gitlinks to commits of another repository, only files are compared
+            // like `get_blob_ids_by_path` does for the trees
+            if entry.mode & 0o170000 == 0o160000 {
+                continue;
+            }
+            let path = String::from_utf8_lossy(&entry.path).into_owned();
+            // The stage is in bits 12 and 13 of the flags, 0 for entries without conflict
+            if (entry.flags >> 12) & 3 == 0 {
+                redone_files.insert(path, entry.id);
+            } else {
+                conflicted.insert(path);
+            }
+        }
+    }
+
+    let mut paths: BTreeSet<&String> = merged_files.keys().collect();
+    match &redone {
+        Some(_) => paths.extend(redone_files.keys()),
+        None => {
+            for files in &parent_files {
+                paths.extend(files.keys());
+            }
+        }
+    }
+    let mut output: Vec<EvilChange> = Vec::new();
+    for path in paths {
+        let merged_id = merged_files.get(path);
+        if redone.is_some() && !conflicted.contains(path) {
+            // Merged cleanly, the merge commit should have the same file
+            let redone_id = redone_files.get(path);
+            if merged_id != redone_id {
+                let (additions, deletions) =
+                    get_line_stats(repository, redone_id.copied(), merged_id.copied())?;
+                output.push(EvilChange {
+                    path: path.clone(),
+                    conflicted: false,
+                    additions,
+                    deletions,
+                });
+            }
+            continue;
+        }
+        // Files identical to one of the parents were resolved by picking a side
+        if parent_files.iter().any(|x| x.get(path) == merged_id) {
+            continue;
+        }
+        let merged_id = match merged_id {
+            Some(merged_id) => *merged_id,
+            None => continue,
+        };
+        let additions = count_lines_in_no_parent(repository, merged_id, path, &parent_files)?;
+        if additions > 0 {
+            output.push(EvilChange {
+                path: path.clone(),
+                conflicted: redone.is_some(),
+                additions,
+                deletions: 0,
+            });
+        }
+    }
+    Ok(output)
+}
+
+fn get_blob_ids_by_path(commit: &Commit) -> Result<BTreeMap<String, Oid>, String> {
+    let tree = commit
+        .tree()
+        .map_err(|_| "Could not extract tree of commit".to_owned())?;
+    let mut output: BTreeMap<String, Oid> = BTreeMap::new();
+    let result = tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            let name = String::from_utf8_lossy(entry.name_bytes());
+            output.insert(format!("{}{}", root, name), entry.id());
+        }
+        TreeWalkResult::Ok
+    });
+    match result {
+        Ok(_) => Ok(output),
+        Err(err) => Err(format!(
+            "Could not read tree of {}: {}",
+            commit.id(),
+            err.message()
+        )),
+    }
+}
+
+fn get_line_stats(
+    repository: &Repository,
+    old_id: Option<Oid>,
+    new_id: Option<Oid>,
+) -> Result<(usize, usize), String> {
+    // Lines added and deleted between two versions of a file, a missing version is empty
+    let error = |err: git2::Error| format!("Could not read diff: {}", err.message());
+    let read = |id: Option<Oid>| -> Result<Vec<u8>, String> {
+        match id {
+            Some(id) => Ok(repository.find_blob(id).map_err(error)?.content().to_vec()),
+            None => Ok(Vec::new()),
+        }
+    };
+    let (old_contents, new_contents) = (read(old_id)?, read(new_id)?);
+    let patch =
+        Patch::from_buffers(&old_contents, None, &new_contents, None, None).map_err(error)?;
+    let (_, additions, deletions) = patch.line_stats().map_err(error)?;
+    Ok((additions, deletions))
+}
+
+fn count_lines_in_no_parent(
+    repository: &Repository,
+    merged_id: Oid,
+    path: &str,
+    parent_files: &[BTreeMap<String, Oid>],
+) -> Result<usize, String> {
+    // Lines of the merged file which are not in the file at any of the parents. Binary files have
+    // no lines, they count as one when they match no parent
+    let read = |id: Oid| {
+        repository
+            .find_blob(id)
+            .map_err(|err| format!("Could not read {}: {}", path, err.message()))
+    };
+    let merged_blob = read(merged_id)?;
+    if merged_blob.is_binary() {
+        return Ok(1);
+    }
+    let mut parent_lines: HashSet<Vec<u8>> = HashSet::new();
+    for files in parent_files {
+        if let Some(id) = files.get(path) {
+            let blob = read(*id)?;
+            parent_lines.extend(blob.content().split(|x| *x == b'\n').map(|x| x.to_vec()));
+        }
+    }
+    Ok(merged_blob
+        .content()
+        .split(|x| *x == b'\n')
+        .filter(|x| !x.is_empty() && !parent_lines.contains(*x))
+        .count())
+}
@@ -0,0 +1,164 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use git2::{Commit, Repository};
+use serde::Serialize;
+
+use crate::{
+    diff::{get_changes_by_path, get_diff_with_first_parent, ChangeKind, FileChurn},
+    object_cache::ObjectCache,
+    walker::{CommitSummary, RefKind},
+};
+
+/*
+Exports of the timeline to the formats of other tools, so a range of frames in gitplay can be
+taken elsewhere.
+ */
+
+pub fn get_gource_lines(repository: &Repository, commit: &Commit) -> Result<Vec<String>, String> {
+    // Lines of Gource's custom log format for one commit, one per file changed:
+    // timestamp|author|A, M or D|/path
+    // Gource splits lines on "|", so it can not be part of the author's name
+    let author = String::from_utf8_lossy(commit.author().name_bytes()).replace('|', " ");
+    let time = commit.time().seconds();
+    let diff = get_diff_with_first_parent(repository, commit)?;
+    Ok(get_changes_by_path(&diff)
+        .into_iter()
+        .map(|change| {
+            let kind = match change.kind {
+                ChangeKind::Added => "A",
+                ChangeKind::Modified => "M",
+                ChangeKind::Deleted => "D",
+            };
+            format!("{}|{}|{}|/{}", time, author, kind, change.path)
+        })
+        .collect())
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AuthorActivity {
+    pub author: String,
+    pub commits: usize,
+    // Summed over the commits, so a file changed in two commits counts twice
+    pub files_changed: usize,
+    pub additions: usize,
+    pub deletions: usize,
+    pub first_commit_time: i64,
+    pub last_commit_time: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FrameSize {
+    pub commit_index: usize,
+    pub commit_id: String,
+    pub size: usize,
+    pub file_count: usize,
+}
+
+/*
+Report is the statistics of a range of frames, shaped as tables to be taken out of gitplay: the
+commits, how often and how much each file changed, the activity of each author and the size of
+the whole tree at each frame.
+ */
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub start_index: usize,
+    pub end_index: usize,
+    pub commits: Vec<CommitSummary>,
+    pub file_changes: Vec<FileChurn>,
+    pub authors: Vec<AuthorActivity>,
+    pub frame_sizes: Vec<FrameSize>,
+}
+
+impl Report {
+    pub fn write_to_folder(&self, folder: &Path) -> Result<Vec<PathBuf>, String> {
+        // Write a CSV file for each table and all of them together as report.json
+        // Returns the paths of the files written
+        if let Err(err) = fs::create_dir_all(folder) {
+            return Err(format!("Could not create {}: {}", folder.display(), err));
+        }
+        let output = vec![
+            write_csv(
+                &folder.join("commits.csv"),
+                &self.commits.iter().map(CommitRow::from).collect::<Vec<_>>(),
+            )?,
+            write_csv(&folder.join("file_changes.csv"), &self.file_changes)?,
+            write_csv(&folder.join("authors.csv"), &self.authors)?,
+            write_csv(&folder.join("frame_sizes.csv"), &self.frame_sizes)?,
+        ];
+        let json_path = folder.join("report.json");
+        let json = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        match fs::write(&json_path, json) {
+            Ok(_) => Ok([output, vec![json_path]].concat()),
+            Err(err) => Err(format!("Could not write {}: {}", json_path.display(), err)),
+        }
+    }
+}
+
+// CSV cells can not hold lists, so decorations are written the way `git log --decorate` prints them
+#[derive(Serialize)]
+struct CommitRow<'a> {
+    commit_index: usize,
+    commit_id: &'a str,
+    commit_message: &'a str,
+    author: &'a str,
+    time: i64,
+    decorations: String,
+}
+
+impl<'a> From<&'a CommitSummary> for CommitRow<'a> {
+    fn from(commit: &'a CommitSummary) -> Self {
+        let decorations: Vec<String> = commit
+            .decorations
+            .iter()
+            .map(|x| match (x.kind, x.is_head) {
+                (RefKind::Head, _) => x.name.clone(),
+                (RefKind::Tag, _) => format!("tag: {}", x.name),
+                (_, true) => format!("HEAD -> {}", x.name),
+                (_, false) => x.name.clone(),
+            })
+            .collect();
+        CommitRow {
+            commit_index: commit.commit_index,
+            commit_id: &commit.commit_id,
+            commit_message: &commit.commit_message,
+            author: &commit.author,
+            time: commit.time,
+            decorations: decorations.join(", "),
+        }
+    }
+}
+
+fn write_csv<T: Serialize>(path: &Path, rows: &[T]) -> Result<PathBuf, String> {
+    let error =
+        |err: &dyn std::fmt::Display| format!("Could not write {}: {}", path.display(), err);
+    let mut writer = csv::Writer::from_path(path).map_err(|err| error(&err))?;
+    for row in rows {
+        writer.serialize(row).map_err(|err| error(&err))?;
+    }
+    writer.flush().map_err(|err| error(&err))?;
+    Ok(path.to_path_buf())
+}
+
+pub(crate) fn get_frame_size(
+    repository: &Repository,
+    commit: &Commit,
+    commit_index: usize,
+    object_cache: &mut ObjectCache,
+) -> Result<FrameSize, String> {
+    // Size and number of files of the whole tree of a commit, read through the object cache
+    match commit.tree() {
+        Ok(tree) => {
+            let stats = object_cache.get_directory_stats(repository, tree.id(), false);
+            Ok(FrameSize {
+                commit_index,
+                commit_id: commit.id().to_string(),
+                size: stats.size,
+                file_count: stats.file_count,
+            })
+        }
+        Err(_) => Err("Could not extract tree of commit".to_owned()),
+    }
+}
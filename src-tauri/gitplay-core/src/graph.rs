@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::walker::CommitFrame;
+
+/*
+The commit graph is laid out like `git log --graph`: each commit of the timeline is a row, in a
+column (lane), with the lines going from it towards its parents. The layout is computed once for
+the whole timeline, from the newest commit down, so the branch HEAD is on keeps the first column.
+
+Edges of a row go from this row to the row of the previous (older) commit in the timeline. A line
+to a parent further away continues as a straight edge in the rows in between.
+ */
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+pub struct GraphEdge {
+    pub from_column: usize,
+    pub to_column: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GraphRow {
+    pub commit_index: usize,
+    pub column: usize,
+    // Number of columns in use at this row, to size the drawing
+    pub width: usize,
+    pub edges: Vec<GraphEdge>,
+    // More than one parent in the timeline
+    pub is_merge: bool,
+    // More than one child in the timeline, where branches started
+    pub is_fork: bool,
+}
+
+pub fn get_graph_layout(
+    commits: &[CommitFrame],
+    commit_ids: &HashMap<String, usize>,
+) -> Vec<GraphRow> {
+    // Parents outside of the timeline, like those cut by a shallow clone, are left out
+    let parents: Vec<Vec<usize>> = commits
+        .iter()
+        .map(|commit| {
+            commit
+                .get_parents()
+                .iter()
+                .filter_map(|x| commit_ids.get(x).copied())
+                .collect()
+        })
+        .collect();
+    let mut children_count: Vec<usize> = vec![0; commits.len()];
+    for commit_parents in &parents {
+        for parent in commit_parents {
+            children_count[*parent] += 1;
+        }
+    }
+
+    // Each lane holds the commit it is heading to, a commit is never in more than one lane
+    let mut lanes: Vec<Option<usize>> = Vec::new();
+    let mut output: Vec<GraphRow> = Vec::with_capacity(commits.len());
+    for commit_index in (0..commits.len()).rev() {
+        let column = match lanes.iter().position(|x| *x == Some(commit_index)) {
+            Some(column) => column,
+            // Nothing leads here, this is the tip of a branch
+            None => take_free_lane(&mut lanes),
+        };
+        lanes[column] = None;
+        // Lanes which continue straight through this row
+        let mut edges: Vec<GraphEdge> = lanes
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.is_some())
+            .map(|(lane, _)| GraphEdge {
+                from_column: lane,
+                to_column: lane,
+            })
+            .collect();
+
+        for (parent_number, parent) in parents[commit_index].iter().enumerate() {
+            let lane = match lanes.iter().position(|x| *x == Some(*parent)) {
+                // Another child already leads to this parent in a column to the right, so the
+                // first parent pulls that line into the column of the commit
+                Some(lane) if parent_number == 0 && lane > column => {
+                    lanes[lane] = None;
+                    lanes[column] = Some(*parent);
+                    for edge in edges.iter_mut().filter(|x| x.from_column == lane) {
+                        edge.to_column = column;
+                    }
+                    column
+                }
+                // Another child already leads to this parent, join its lane
+                Some(lane) => lane,
+                // The first parent continues in the column of the commit
+                None if parent_number == 0 => {
+                    lanes[column] = Some(*parent);
+                    column
+                }
+                None => {
+                    let lane = take_free_lane(&mut lanes);
+                    lanes[lane] = Some(*parent);
+                    lane
+                }
+            };
+            edges.push(GraphEdge {
+                from_column: column,
+                to_column: lane,
+            });
+        }
+        while lanes.last() == Some(&None) {
+            lanes.pop();
+        }
+
+        edges.sort_by_key(|x| (x.from_column, x.to_column));
+        let width = edges
+            .iter()
+            .map(|x| x.from_column.max(x.to_column) + 1)
+            .max()
+            .unwrap_or(0)
+            .max(column + 1);
+        output.push(GraphRow {
+            commit_index,
+            column,
+            width,
+            edges,
+            is_merge: parents[commit_index].len() > 1,
+            is_fork: children_count[commit_index] > 1,
+        });
+    }
+    output.reverse();
+    output
+}
+
+fn take_free_lane(lanes: &mut Vec<Option<usize>>) -> usize {
+    match lanes.iter().position(|x| x.is_none()) {
+        Some(lane) => lane,
+        None => {
+            lanes.push(None);
+            lanes.len() - 1
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::{collections::HashMap, fmt::Write};
+
+use git2::{Commit, ObjectType, Repository};
+use serde::Serialize;
+
+use crate::{dates::format_date, export::Report, object_cache::ObjectCache, tags::Tag};
+
+/*
+The HTML report is a single self-contained page about the history of a repository, meant for
+onboarding documentation: an overview, the files changed most often, the top contributors, how
+the major directories grew over time and the list of releases. Styles and charts are inline, so
+the file can be opened or shared without a server.
+ */
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DirectorySizes {
+    pub commit_index: usize,
+    pub time: i64,
+    // Size of each directory at the root of the tree, files at the root are not included
+    pub sizes: HashMap<String, usize>,
+}
+
+pub struct HtmlReport<'a> {
+    pub title: String,
+    pub report: &'a Report,
+    pub directory_timeline: Vec<DirectorySizes>,
+    pub releases: Vec<Tag>,
+}
+
+// Number of rows in the tables of files and contributors, and of lines in the directory chart
+const TOP_FILES: usize = 15;
+const TOP_CONTRIBUTORS: usize = 10;
+const MAJOR_DIRECTORIES: usize = 6;
+const CHART_COLORS: [&str; MAJOR_DIRECTORIES] = [
+    "#2563eb", "#dc2626", "#16a34a", "#d97706", "#7c3aed", "#0891b2",
+];
+
+pub(crate) fn get_directory_sizes(
+    repository: &Repository,
+    commit: &Commit,
+    commit_index: usize,
+    object_cache: &mut ObjectCache,
+) -> Result<DirectorySizes, String> {
+    // Sizes of the directories at the root of the tree of a commit, read through the object cache
+    let tree_id = match commit.tree() {
+        Ok(tree) => tree.id(),
+        Err(_) => return Err("Could not extract tree of commit".to_owned()),
+    };
+    let mut sizes: HashMap<String, usize> = HashMap::new();
+    for entry in object_cache.get_tree_children(repository, tree_id).iter() {
+        if entry.kind == Some(ObjectType::Tree) {
+            let stats = object_cache.get_directory_stats(repository, entry.object_id, false);
+            sizes.insert(entry.name.clone(), stats.size);
+        }
+    }
+    Ok(DirectorySizes {
+        commit_index,
+        time: commit.time().seconds(),
+        sizes,
+    })
+}
+
+pub fn render_html_report(html_report: &HtmlReport) -> String {
+    let report = html_report.report;
+    let title = escape(&html_report.title);
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{} - gitplay report</title>\n<style>{}</style>\n</head>\n<body>\n\
+         <h1>{}</h1>\n",
+        title, STYLE, title
+    );
+
+    // Overview
+    let first_time = report.commits.first().map(|x| x.time);
+    let last_time = report.commits.last().map(|x| x.time);
+    let last_frame = report.frame_sizes.last();
+    html.push_str("<h2>Overview</h2>\n<dl class=\"overview\">\n");
+    let mut overview_item = |label: &str, value: String| {
+        let _ = writeln!(html, "<dt>{}</dt><dd>{}</dd>", label, escape(&value));
+    };
+    overview_item("Commits", report.commits.len().to_string());
+    // The end of the report range is not included, the label shows the last frame in it
+    overview_item(
+        "Frames",
+        if report.end_index > report.start_index {
+            format!("{} to {}", report.start_index, report.end_index - 1)
+        } else {
+            "none".to_owned()
+        },
+    );
+    overview_item(
+        "First commit",
+        first_time.map_or(String::new(), format_date),
+    );
+    overview_item("Last commit", last_time.map_or(String::new(), format_date));
+    overview_item("Contributors", report.authors.len().to_string());
+    overview_item("Files changed", report.file_changes.len().to_string());
+    overview_item(
+        "Files in the last frame",
+        last_frame.map_or(0, |x| x.file_count).to_string(),
+    );
+    overview_item(
+        "Size of the last frame",
+        format_size(last_frame.map_or(0, |x| x.size)),
+    );
+    html.push_str("</dl>\n");
+
+    // Hotspots, the files changed in the most commits
+    html.push_str("<h2>Hotspots</h2>\n");
+    let most_commits = report.file_changes.first().map_or(1, |x| x.commits.max(1));
+    html.push_str(
+        "<table>\n<tr><th>File</th><th>Commits</th><th>Lines added</th>\
+         <th>Lines deleted</th></tr>\n",
+    );
+    for file in report.file_changes.iter().take(TOP_FILES) {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&file.path),
+            bar(file.commits, most_commits),
+            file.additions,
+            file.deletions
+        );
+    }
+    html.push_str("</table>\n");
+
+    // Top contributors, by number of commits
+    html.push_str("<h2>Top contributors</h2>\n");
+    let most_commits = report.authors.first().map_or(1, |x| x.commits.max(1));
+    html.push_str(
+        "<table>\n<tr><th>Author</th><th>Commits</th><th>Lines added</th>\
+         <th>Lines deleted</th><th>Active</th></tr>\n",
+    );
+    for author in report.authors.iter().take(TOP_CONTRIBUTORS) {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{} to {}</td></tr>",
+            escape(&author.author),
+            bar(author.commits, most_commits),
+            author.additions,
+            author.deletions,
+            format_date(author.first_commit_time),
+            format_date(author.last_commit_time)
+        );
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Major directories</h2>\n");
+    html.push_str(&render_directory_chart(&html_report.directory_timeline));
+
+    html.push_str("<h2>Releases</h2>\n");
+    if html_report.releases.is_empty() {
+        html.push_str("<p>This repository has no tags.</p>\n");
+    } else {
+        html.push_str("<table>\n<tr><th>Tag</th><th>Date</th><th>Frame</th><th>Commit</th></tr>\n");
+        for release in &html_report.releases {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code></td></tr>",
+                escape(&release.name),
+                format_date(release.time),
+                release
+                    .commit_index
+                    .map_or("not in timeline".to_owned(), |x| x.to_string()),
+                &release.commit_id[..release.commit_id.len().min(10)]
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<footer>Generated by gitplay</footer>\n</body>\n</html>\n");
+    html
+}
+
+fn render_directory_chart(directory_timeline: &[DirectorySizes]) -> String {
+    // A line chart of the size of the largest directories (as of the last frame) over time
+    let last = match directory_timeline.last() {
+        Some(last) if !last.sizes.is_empty() => last,
+        _ => return "<p>No directories in this range.</p>\n".to_owned(),
+    };
+    let mut directories: Vec<(&String, &usize)> = last.sizes.iter().collect();
+    directories.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    directories.truncate(MAJOR_DIRECTORIES);
+
+    let (width, height) = (720.0, 240.0);
+    let largest = directory_timeline
+        .iter()
+        .flat_map(|frame| directories.iter().filter_map(|x| frame.sizes.get(x.0)))
+        .max()
+        .copied()
+        .unwrap_or(1)
+        .max(1) as f64;
+    let step = width / (directory_timeline.len().max(2) - 1) as f64;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg viewBox=\"0 0 {} {}\" class=\"chart\" role=\"img\">",
+        width, height
+    );
+    for (index, (name, _)) in directories.iter().enumerate() {
+        let points: Vec<String> = directory_timeline
+            .iter()
+            .enumerate()
+            .map(|(frame_index, frame)| {
+                let size = frame.sizes.get(*name).copied().unwrap_or(0) as f64;
+                format!(
+                    "{:.1},{:.1}",
+                    frame_index as f64 * step,
+                    height - size / largest * height
+                )
+            })
+            .collect();
+        let _ = writeln!(
+            svg,
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"/>",
+            CHART_COLORS[index],
+            points.join(" ")
+        );
+    }
+    svg.push_str("</svg>\n<ul class=\"legend\">\n");
+    for (index, (name, size)) in directories.iter().enumerate() {
+        let _ = writeln!(
+            svg,
+            "<li><span style=\"background:{}\"></span>{}/ ({})</li>",
+            CHART_COLORS[index],
+            escape(name),
+            format_size(**size)
+        );
+    }
+    let _ = writeln!(
+        svg,
+        "</ul>\n<p class=\"note\">From {} to {}</p>",
+        format_date(directory_timeline[0].time),
+        format_date(last.time)
+    );
+    svg
+}
+
+fn bar(value: usize, largest: usize) -> String {
+    format!(
+        "<span class=\"bar\" style=\"width:{}px\"></span>{}",
+        value * 120 / largest,
+        value
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_size(size: usize) -> String {
+    match size {
+        0..=1023 => format!("{} B", size),
+        1024..=1048575 => format!("{:.1} KB", size as f64 / 1024.0),
+        _ => format!("{:.1} MB", size as f64 / 1048576.0),
+    }
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:960px;margin:2em auto;\
+padding:0 1em;color:#1f2937}h1{margin-bottom:0.2em}h2{margin-top:2em;border-bottom:1px solid \
+#e5e7eb}table{border-collapse:collapse;width:100%}th,td{text-align:left;padding:4px 8px;\
+border-bottom:1px solid #f3f4f6}td:first-child{word-break:break-all}.bar{display:inline-block;\
+height:10px;background:#93c5fd;margin-right:6px}.overview{display:grid;\
+grid-template-columns:max-content auto;gap:4px 16px}.overview dd{margin:0}.chart{width:100%;\
+height:auto;border:1px solid #e5e7eb}.legend{list-style:none;padding:0}.legend li{display:inline-block;\
+margin-right:16px}.legend span{display:inline-block;width:12px;height:12px;margin-right:4px}\
+.note,footer{color:#6b7280;font-size:0.9em}footer{margin-top:3em}";
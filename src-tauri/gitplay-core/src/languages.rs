@@ -0,0 +1,362 @@
+use std::{
+    collections::HashMap,
+    ops::{AddAssign, SubAssign},
+    path::Path,
+};
+
+use git2::{Delta, Oid, Repository, Tree};
+use serde::Serialize;
+
+use crate::walker::get_commit_tree;
+
+/*
+Language statistics of a frame: the number of files and code, comment and blank lines for each
+language in the tree. Statistics are counted once per blob and a frame is derived from the frame
+before it by only applying the files which changed in between.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct LanguageStats {
+    files: usize,
+    code_lines: usize,
+    comment_lines: usize,
+    blank_lines: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FrameLanguageStats {
+    commit_id: String,
+    commit_index: usize,
+    languages: HashMap<String, LanguageStats>,
+}
+
+struct LanguageSyntax {
+    name: &'static str,
+    extensions: &'static [&'static str],
+    interpreters: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+}
+
+const C_STYLE: Option<(&str, &str)> = Some(("/*", "*/"));
+const HTML_STYLE: Option<(&str, &str)> = Some(("<!--", "-->"));
+
+const LANGUAGES: &[LanguageSyntax] = &[
+    LanguageSyntax {
+        name: "Rust",
+        extensions: &["rs"],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: C_STYLE,
+    },
+    LanguageSyntax {
+        name: "Python",
+        extensions: &["py", "pyi"],
+        interpreters: &["python", "python2", "python3"],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    LanguageSyntax {
+        name: "TypeScript",
+        extensions: &["ts", "tsx", "mts", "cts"],
+        interpreters: &["ts-node", "deno"],
+        line_comments: &["//"],
+        block_comment: C_STYLE,
+    },
+    LanguageSyntax {
+        name: "JavaScript",
+        extensions: &["js", "jsx", "mjs", "cjs"],
+        interpreters: &["node"],
+        line_comments: &["//"],
+        block_comment: C_STYLE,
+    },
+    LanguageSyntax {
+        name: "Go",
+        extensions: &["go"],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: C_STYLE,
+    },
+    LanguageSyntax {
+        name: "C",
+        extensions: &["c", "h"],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: C_STYLE,
+    },
+    LanguageSyntax {
+        name: "C++",
+        extensions: &["cc", "cpp", "cxx", "hh", "hpp", "hxx"],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: C_STYLE,
+    },
+    LanguageSyntax {
+        name: "Java",
+        extensions: &["java"],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: C_STYLE,
+    },
+    LanguageSyntax {
+        name: "Ruby",
+        extensions: &["rb"],
+        interpreters: &["ruby"],
+        line_comments: &["#"],
+        block_comment: Some(("=begin", "=end")),
+    },
+    LanguageSyntax {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh"],
+        interpreters: &["sh", "bash", "zsh"],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    LanguageSyntax {
+        name: "Perl",
+        extensions: &["pl", "pm"],
+        interpreters: &["perl"],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    LanguageSyntax {
+        name: "HTML",
+        extensions: &["html", "htm"],
+        interpreters: &[],
+        line_comments: &[],
+        block_comment: HTML_STYLE,
+    },
+    LanguageSyntax {
+        name: "CSS",
+        extensions: &["css", "scss"],
+        interpreters: &[],
+        line_comments: &[],
+        block_comment: C_STYLE,
+    },
+    LanguageSyntax {
+        name: "Markdown",
+        extensions: &["md", "markdown"],
+        interpreters: &[],
+        line_comments: &[],
+        block_comment: HTML_STYLE,
+    },
+    LanguageSyntax {
+        name: "TOML",
+        extensions: &["toml"],
+        interpreters: &[],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    LanguageSyntax {
+        name: "YAML",
+        extensions: &["yml", "yaml"],
+        interpreters: &[],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    LanguageSyntax {
+        name: "JSON",
+        extensions: &["json"],
+        interpreters: &[],
+        line_comments: &[],
+        block_comment: None,
+    },
+];
+
+impl AddAssign for LanguageStats {
+    fn add_assign(&mut self, other: Self) {
+        self.files += other.files;
+        self.code_lines += other.code_lines;
+        self.comment_lines += other.comment_lines;
+        self.blank_lines += other.blank_lines;
+    }
+}
+
+impl SubAssign for LanguageStats {
+    fn sub_assign(&mut self, other: Self) {
+        self.files = self.files.saturating_sub(other.files);
+        self.code_lines = self.code_lines.saturating_sub(other.code_lines);
+        self.comment_lines = self.comment_lines.saturating_sub(other.comment_lines);
+        self.blank_lines = self.blank_lines.saturating_sub(other.blank_lines);
+    }
+}
+
+fn detect_language(path: &str, contents: &[u8]) -> Option<&'static LanguageSyntax> {
+    // We detect the language by the file extension, or by the interpreter in the shebang line
+    match Path::new(path).extension().and_then(|x| x.to_str()) {
+        Some(extension) => {
+            let extension = extension.to_lowercase();
+            LANGUAGES
+                .iter()
+                .find(|x| x.extensions.contains(&extension.as_str()))
+        }
+        None => {
+            let first_line = contents.split(|x| *x == b'\n').next()?;
+            let first_line = std::str::from_utf8(first_line).ok()?.strip_prefix("#!")?;
+            let mut words = first_line.split_whitespace();
+            let mut interpreter = Path::new(words.next()?).file_name()?.to_str()?;
+            // `#!/usr/bin/env python3` names the interpreter as the argument of env
+            if interpreter == "env" {
+                interpreter = words.find(|x| !x.starts_with('-'))?;
+            }
+            LANGUAGES
+                .iter()
+                .find(|x| x.interpreters.contains(&interpreter))
+        }
+    }
+}
+
+fn count_lines(syntax: &LanguageSyntax, contents: &str) -> LanguageStats {
+    let mut stats = LanguageStats {
+        files: 1,
+        ..LanguageStats::default()
+    };
+    let mut in_block_comment = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            stats.blank_lines += 1;
+            continue;
+        }
+        if in_block_comment {
+            stats.comment_lines += 1;
+            if let Some((_, end)) = syntax.block_comment {
+                in_block_comment = !line.contains(end);
+            }
+            continue;
+        }
+        if syntax.line_comments.iter().any(|x| line.starts_with(x)) {
+            stats.comment_lines += 1;
+            continue;
+        }
+        match syntax.block_comment {
+            Some((start, end)) if line.starts_with(start) => {
+                stats.comment_lines += 1;
+                in_block_comment = !line[start.len()..].contains(end);
+            }
+            Some((start, end)) => {
+                stats.code_lines += 1;
+                // A block comment may be opened after some code on the same line
+                if let Some(position) = line.rfind(start) {
+                    in_block_comment = !line[position + start.len()..].contains(end);
+                }
+            }
+            None => stats.code_lines += 1,
+        }
+    }
+    stats
+}
+
+#[derive(Default)]
+pub struct LanguageStatsCache {
+    stats_by_blob: HashMap<(Oid, String), Option<(&'static str, LanguageStats)>>,
+    frames: Vec<(String, HashMap<String, LanguageStats>)>,
+}
+
+impl LanguageStatsCache {
+    pub fn new() -> Self {
+        LanguageStatsCache::default()
+    }
+
+    fn get_stats_for_blob(
+        &mut self,
+        repository: &Repository,
+        path: &str,
+        object_id: Oid,
+    ) -> Option<(&'static str, LanguageStats)> {
+        // The same blob may be counted as different languages depending on its file extension
+        let key = (
+            object_id,
+            path.rsplit('/').next().unwrap_or(path).to_owned(),
+        );
+        if let Some(stats) = self.stats_by_blob.get(&key) {
+            return *stats;
+        }
+        let stats = match repository.find_blob(object_id) {
+            Ok(blob) if !blob.is_binary() => detect_language(path, blob.content()).map(|x| {
+                (
+                    x.name,
+                    count_lines(x, &String::from_utf8_lossy(blob.content())),
+                )
+            }),
+            _ => None,
+        };
+        self.stats_by_blob.insert(key, stats);
+        stats
+    }
+
+    pub fn get_language_stats(
+        &mut self,
+        repository: &Repository,
+        commit_ids: &[String],
+        start_index: usize,
+        end_index: usize,
+    ) -> Result<Vec<FrameLanguageStats>, String> {
+        // Frames are computed in order, so we first catch up with all the frames before the
+        // requested range, each one derived from the one before it
+        if self.frames.len() > commit_ids.len()
+            || self
+                .frames
+                .iter()
+                .zip(commit_ids)
+                .any(|(frame, commit_id)| frame.0 != *commit_id)
+        {
+            self.frames = Vec::new();
+        }
+
+        let mut previous_tree: Option<Tree> = match self.frames.last() {
+            Some((commit_id, _)) => Some(get_commit_tree(repository, commit_id)?),
+            None => None,
+        };
+        while self.frames.len() < end_index.min(commit_ids.len()) {
+            let commit_id = &commit_ids[self.frames.len()];
+            let tree = get_commit_tree(repository, commit_id)?;
+            let diff = repository
+                .diff_tree_to_tree(previous_tree.as_ref(), Some(&tree), None)
+                .map_err(|err| format!("Could not diff commit: {}", err.message()))?;
+
+            let mut languages = match self.frames.last() {
+                Some((_, languages)) => languages.clone(),
+                None => HashMap::new(),
+            };
+            for delta in diff.deltas() {
+                if delta.status() != Delta::Added {
+                    if let Some(path) = delta.old_file().path() {
+                        let path = path.to_string_lossy();
+                        if let Some((name, stats)) =
+                            self.get_stats_for_blob(repository, &path, delta.old_file().id())
+                        {
+                            if let Some(existing) = languages.get_mut(name) {
+                                *existing -= stats;
+                            }
+                        }
+                    }
+                }
+                if delta.status() != Delta::Deleted {
+                    if let Some(path) = delta.new_file().path() {
+                        let path = path.to_string_lossy();
+                        if let Some((name, stats)) =
+                            self.get_stats_for_blob(repository, &path, delta.new_file().id())
+                        {
+                            *languages.entry(name.to_owned()).or_default() += stats;
+                        }
+                    }
+                }
+            }
+            languages.retain(|_, stats| stats.files > 0);
+
+            self.frames.push((commit_id.clone(), languages));
+            previous_tree = Some(tree);
+        }
+
+        let end_index = end_index.min(self.frames.len());
+        Ok(self.frames[start_index.min(end_index)..end_index]
+            .iter()
+            .enumerate()
+            .map(|(index, (commit_id, languages))| FrameLanguageStats {
+                commit_id: commit_id.clone(),
+                commit_index: start_index + index,
+                languages: languages.clone(),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,28 @@
+//! The backend of gitplay, shared by the desktop application and the command line interface.
+//!
+//! [`GitplayState`] is a session on one repository: open it with [`GitplayState::open`], load
+//! the timeline with [`GitplayState::prepare_cache`] and then query commits ([`CommitFrame`],
+//! [`CommitSummary`]), their file trees ([`FileTree`], [`FileBlob`]) and the changes between
+//! them ([`FileChurn`]). None of this depends on Tauri.
+pub mod annotations;
+pub mod buckets;
+pub mod cache;
+mod dates;
+pub mod diff;
+pub mod export;
+pub mod graph;
+pub mod html_report;
+pub mod languages;
+pub mod notes;
+mod object_cache;
+mod path_filter;
+mod scan;
+pub mod search;
+pub mod symbols;
+pub mod tags;
+pub mod tours;
+pub mod walker;
+
+pub use cache::GitplayState;
+pub use diff::FileChurn;
+pub use walker::{CommitFrame, CommitSummary, FileBlob, FileTree};
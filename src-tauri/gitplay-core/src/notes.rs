@@ -0,0 +1,151 @@
+use git2::{ConfigLevel, ErrorCode, Oid, Repository, Signature};
+use serde::Serialize;
+
+/*
+Git notes attach text to commits without changing them, under refs like refs/notes/commits (the
+default of `git notes`). Some repositories already carry review links, build results or deploy
+records this way. Which notes refs are read into a CommitFrame is configured per repository, in
+its own Git config as `gitplay.notesRef` (one value per ref), so the choice survives reopening.
+ */
+
+pub const DEFAULT_NOTES_REF: &str = "refs/notes/commits";
+const NOTES_REFS_CONFIG: &str = "gitplay.notesRef";
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct CommitNote {
+    pub notes_ref: String,
+    pub message: String,
+}
+
+pub fn get_full_notes_ref(name: &str) -> Result<String, String> {
+    // Accept short names like "commits" the way `git notes --ref` does
+    let name = name.trim();
+    let full_name = if name.starts_with("refs/notes/") {
+        name.to_owned()
+    } else if let Some(rest) = name.strip_prefix("notes/") {
+        format!("refs/notes/{}", rest)
+    } else {
+        format!("refs/notes/{}", name)
+    };
+    if full_name == "refs/notes/" || !git2::Reference::is_valid_name(&full_name) {
+        return Err(format!("{} is not a valid notes ref", name));
+    }
+    Ok(full_name)
+}
+
+pub fn list_notes_refs(repository: &Repository) -> Vec<String> {
+    // All notes refs present in the repository, like those fetched from a remote
+    let mut output: Vec<String> = match repository.references_glob("refs/notes/*") {
+        Ok(references) => references
+            .flatten()
+            .filter_map(|x| x.name().map(|x| x.to_owned()))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    output.sort();
+    output
+}
+
+pub fn read_configured_notes_refs(repository: &Repository) -> Vec<String> {
+    // Notes refs chosen earlier for this repository, in the order they were chosen. Values which
+    // are not valid notes refs, like ones edited by hand, are skipped
+    let mut output: Vec<String> = Vec::new();
+    if let Ok(config) = repository.config() {
+        if let Ok(mut entries) = config.multivar(NOTES_REFS_CONFIG, None) {
+            while let Some(Ok(entry)) = entries.next() {
+                if let Some(Ok(full_name)) = entry.value().map(get_full_notes_ref) {
+                    if !output.contains(&full_name) {
+                        output.push(full_name);
+                    }
+                }
+            }
+        }
+    }
+    output
+}
+
+pub fn write_configured_notes_refs(
+    repository: &Repository,
+    notes_refs: &[String],
+) -> Result<(), String> {
+    // Replace the notes refs stored in the config of the repository itself, not the global one
+    let mut config = match repository
+        .config()
+        .and_then(|x| x.open_level(ConfigLevel::Local))
+    {
+        Ok(config) => config,
+        Err(err) => return Err(format!("Could not open Git config: {}", err.message())),
+    };
+    match config.remove_multivar(NOTES_REFS_CONFIG, ".*") {
+        Err(err) if err.code() != ErrorCode::NotFound => {
+            return Err(format!("Could not write Git config: {}", err.message()))
+        }
+        _ => {}
+    }
+    for notes_ref in notes_refs {
+        // A pattern matching no existing value adds a new one
+        if let Err(err) = config.set_multivar(NOTES_REFS_CONFIG, "^$", notes_ref) {
+            return Err(format!("Could not write Git config: {}", err.message()));
+        }
+    }
+    Ok(())
+}
+
+pub fn get_commit_notes(
+    repository: &Repository,
+    commit_id: Oid,
+    notes_refs: &[String],
+) -> Vec<CommitNote> {
+    // Notes of a commit in the given refs, in the order of the refs. Refs which do not exist or
+    // have no note for this commit are skipped
+    notes_refs
+        .iter()
+        .filter_map(|notes_ref| {
+            repository
+                .find_note(Some(notes_ref), commit_id)
+                .ok()
+                .map(|note| CommitNote {
+                    notes_ref: notes_ref.clone(),
+                    message: String::from_utf8_lossy(note.message_bytes()).into_owned(),
+                })
+        })
+        .collect()
+}
+
+pub fn write_note(
+    repository: &Repository,
+    notes_ref: &str,
+    commit_id: &str,
+    message: &str,
+) -> Result<(), String> {
+    // Add or replace the note of a commit, an empty message removes the note
+    let commit_id = match Oid::from_str(commit_id).and_then(|x| repository.find_commit(x)) {
+        Ok(commit) => commit.id(),
+        Err(_) => return Err(format!("Commit {} does not exist", commit_id)),
+    };
+    // Notes are commits in the notes ref, they need an author even if Git is not configured
+    let signature = match repository.signature() {
+        Ok(signature) => signature,
+        Err(_) => Signature::now("gitplay", "gitplay@localhost").map_err(|x| x.to_string())?,
+    };
+    let result = if message.is_empty() {
+        match repository.find_note(Some(notes_ref), commit_id) {
+            Ok(_) => repository
+                .note_delete(commit_id, Some(notes_ref), &signature, &signature)
+                .map(|_| ()),
+            Err(_) => Ok(()),
+        }
+    } else {
+        repository
+            .note(
+                &signature,
+                &signature,
+                Some(notes_ref),
+                commit_id,
+                message,
+                true,
+            )
+            .map(|_| ())
+    };
+    result.map_err(|err| format!("Could not write Git note: {}", err.message()))
+}
@@ -0,0 +1,272 @@
+use std::{collections::HashMap, sync::Arc};
+
+use git2::{ObjectType, Oid, Repository};
+use serde::Serialize;
+
+/*
+ObjectCache keeps what we learn about Git objects across frames. Objects are immutable, so results
+keyed by object id never go stale: a directory which is unchanged between two commits has the same
+tree object id and its aggregated statistics are simply reused.
+ */
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct DirectoryStats {
+    pub size: usize,
+    pub file_count: usize,
+    // None when line counts were not requested for this tree
+    pub line_count: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TreeEntry {
+    // Path of the folder containing this entry, relative to the listed tree, like "src/" or ""
+    pub root: String,
+    pub name: String,
+    pub object_id: Oid,
+    pub kind: Option<ObjectType>,
+}
+
+#[derive(Default)]
+pub struct ObjectCache {
+    blob_sizes: HashMap<Oid, usize>,
+    blob_line_counts: HashMap<Oid, usize>,
+    directory_stats: HashMap<Oid, DirectoryStats>,
+    tree_children: HashMap<Oid, Arc<Vec<TreeEntry>>>,
+}
+
+impl TreeEntry {
+    pub fn get_path(&self) -> String {
+        self.root.clone() + &self.name
+    }
+}
+
+impl ObjectCache {
+    pub fn new() -> Self {
+        ObjectCache::default()
+    }
+
+    pub fn get_blob_size(&mut self, repository: &Repository, object_id: Oid) -> usize {
+        // The size is read from the object header, so the blob is never inflated just to find
+        // out its length
+        if let Some(size) = self.blob_sizes.get(&object_id) {
+            return *size;
+        }
+        let size = match repository.odb().and_then(|odb| odb.read_header(object_id)) {
+            Ok((size, _)) => size,
+            Err(_) => 0,
+        };
+        self.blob_sizes.insert(object_id, size);
+        size
+    }
+
+    pub fn get_blob_line_count(&mut self, repository: &Repository, object_id: Oid) -> usize {
+        // Counting lines needs the contents of the blob, binary files have no lines
+        if let Some(line_count) = self.blob_line_counts.get(&object_id) {
+            return *line_count;
+        }
+        let line_count = match repository.find_blob(object_id) {
+            Ok(blob) if !blob.is_binary() => count_lines(blob.content()),
+            _ => 0,
+        };
+        self.blob_line_counts.insert(object_id, line_count);
+        line_count
+    }
+
+    pub fn get_directory_stats(
+        &mut self,
+        repository: &Repository,
+        object_id: Oid,
+        with_line_counts: bool,
+    ) -> DirectoryStats {
+        // Recursive size, number of files and number of lines of the tree with the given id
+        if let Some(stats) = self.directory_stats.get(&object_id) {
+            if !with_line_counts || stats.line_count.is_some() {
+                return *stats;
+            }
+        }
+        let mut stats = DirectoryStats {
+            line_count: with_line_counts.then_some(0),
+            ..DirectoryStats::default()
+        };
+        for item in self.get_tree_children(repository, object_id).iter() {
+            match item.kind {
+                Some(ObjectType::Blob) => {
+                    stats.size += self.get_blob_size(repository, item.object_id);
+                    stats.file_count += 1;
+                    if let Some(line_count) = stats.line_count.as_mut() {
+                        *line_count += self.get_blob_line_count(repository, item.object_id);
+                    }
+                }
+                Some(ObjectType::Tree) => {
+                    let subtree_stats =
+                        self.get_directory_stats(repository, item.object_id, with_line_counts);
+                    stats.size += subtree_stats.size;
+                    stats.file_count += subtree_stats.file_count;
+                    stats.line_count = stats
+                        .line_count
+                        .zip(subtree_stats.line_count)
+                        .map(|(x, y)| x + y);
+                }
+                _ => {}
+            }
+        }
+        self.directory_stats.insert(object_id, stats);
+        stats
+    }
+
+    pub fn get_tree_children(
+        &mut self,
+        repository: &Repository,
+        object_id: Oid,
+    ) -> Arc<Vec<TreeEntry>> {
+        // Direct children of the tree with the given id
+        if let Some(entries) = self.tree_children.get(&object_id) {
+            return entries.clone();
+        }
+        let entries: Vec<TreeEntry> = match repository.find_tree(object_id) {
+            Ok(tree) => tree
+                .iter()
+                .map(|item| TreeEntry {
+                    root: String::new(),
+                    name: String::from_utf8_lossy(item.name_bytes()).into_owned(),
+                    object_id: item.id(),
+                    kind: item.kind(),
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        let entries = Arc::new(entries);
+        self.tree_children.insert(object_id, entries.clone());
+        entries
+    }
+}
+
+pub(crate) fn count_lines(contents: &[u8]) -> usize {
+    // The last line is counted even when the file does not end with a newline
+    let newlines = contents.iter().filter(|x| **x == b'\n').count();
+    match contents.last() {
+        Some(b'\n') | None => newlines,
+        Some(_) => newlines + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_tree(repository: &Repository, entries: &[(&str, Oid, i32)]) -> Oid {
+        let mut builder = repository.treebuilder(None).unwrap();
+        for (name, object_id, mode) in entries {
+            builder.insert(name, *object_id, *mode).unwrap();
+        }
+        builder.write().unwrap()
+    }
+
+    #[test]
+    fn count_lines_of_edge_cases() {
+        assert_eq!(count_lines(b""), 0);
+        assert_eq!(count_lines(b"one"), 1);
+        assert_eq!(count_lines(b"one\n"), 1);
+        assert_eq!(count_lines(b"one\ntwo"), 2);
+        // Empty lines are lines
+        assert_eq!(count_lines(b"\n"), 1);
+        assert_eq!(count_lines(b"\n\n\n"), 3);
+        // Windows line endings are counted once, a lone "\r" does not end a line
+        assert_eq!(count_lines(b"one\r\ntwo\r\n"), 2);
+        assert_eq!(count_lines(b"one\rtwo\r"), 1);
+    }
+
+    #[test]
+    fn blob_sizes_and_line_counts() {
+        let folder = tempfile::TempDir::new().unwrap();
+        let repository = Repository::init(folder.path()).unwrap();
+        let text = repository.blob(b"one\ntwo\nthree").unwrap();
+        let binary = repository
+            .blob(&[0x89, b'P', b'N', b'G', 0, b'\n', 0])
+            .unwrap();
+        let mut object_cache = ObjectCache::new();
+
+        assert_eq!(object_cache.get_blob_size(&repository, text), 13);
+        assert_eq!(object_cache.get_blob_line_count(&repository, text), 3);
+        // Binary files have no lines
+        assert_eq!(object_cache.get_blob_size(&repository, binary), 7);
+        assert_eq!(object_cache.get_blob_line_count(&repository, binary), 0);
+        // Objects which are not in the repository are empty
+        assert_eq!(object_cache.get_blob_size(&repository, Oid::zero()), 0);
+        assert_eq!(
+            object_cache.get_blob_line_count(&repository, Oid::zero()),
+            0
+        );
+    }
+
+    #[test]
+    fn tree_children_are_direct_entries() {
+        let folder = tempfile::TempDir::new().unwrap();
+        let repository = Repository::init(folder.path()).unwrap();
+        let blob = repository.blob(b"a\n").unwrap();
+        let subtree = make_tree(&repository, &[("inner.txt", blob, 0o100644)]);
+        let tree = make_tree(
+            &repository,
+            &[("a.txt", blob, 0o100644), ("src", subtree, 0o040000)],
+        );
+        let mut object_cache = ObjectCache::new();
+
+        let children = object_cache.get_tree_children(&repository, tree);
+        let names: Vec<(&str, Option<ObjectType>)> =
+            children.iter().map(|x| (x.name.as_str(), x.kind)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("a.txt", Some(ObjectType::Blob)),
+                ("src", Some(ObjectType::Tree))
+            ]
+        );
+        assert!(children.iter().all(|x| x.root.is_empty()));
+        // The same listing is shared instead of read again
+        assert!(Arc::ptr_eq(
+            &children,
+            &object_cache.get_tree_children(&repository, tree)
+        ));
+        assert!(object_cache.get_tree_children(&repository, blob).is_empty());
+    }
+
+    #[test]
+    fn directory_stats_are_recursive() {
+        let folder = tempfile::TempDir::new().unwrap();
+        let repository = Repository::init(folder.path()).unwrap();
+        let first = repository.blob(b"1\n2\n").unwrap();
+        let second = repository.blob(b"3").unwrap();
+        let subtree = make_tree(
+            &repository,
+            &[("a.txt", first, 0o100644), ("b.txt", second, 0o100644)],
+        );
+        // The same subtree at two places is counted twice
+        let tree = make_tree(
+            &repository,
+            &[
+                ("c.txt", first, 0o100644),
+                ("lib", subtree, 0o040000),
+                ("src", subtree, 0o040000),
+            ],
+        );
+        let mut object_cache = ObjectCache::new();
+
+        let stats = object_cache.get_directory_stats(&repository, tree, false);
+        assert_eq!(
+            (stats.size, stats.file_count, stats.line_count),
+            (14, 5, None)
+        );
+        assert_eq!(object_cache.directory_stats[&subtree].file_count, 2);
+        // Stats cached without lines are counted again when lines are asked for
+        let stats = object_cache.get_directory_stats(&repository, tree, true);
+        assert_eq!(
+            (stats.size, stats.file_count, stats.line_count),
+            (14, 5, Some(8))
+        );
+        assert_eq!(
+            object_cache
+                .get_directory_stats(&repository, subtree, false)
+                .line_count,
+            Some(3)
+        );
+    }
+}
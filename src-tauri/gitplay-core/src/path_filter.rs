@@ -0,0 +1,291 @@
+use glob::{MatchOptions, Pattern};
+
+// PathFilter selects the paths of a tree that we are interested in. It is built from a list of
+// rules which follow Git pathspecs, like `git log -- <pathspec>`:
+//   - "src" or "src/" matches that folder and everything within it, recursively, "" matches
+//     every path
+//   - "src/**" matches everything within a folder, but not the folder itself
+//   - "*.rs" or "src/*.rs" are glob patterns matched against the whole path, where "*" also
+//     matches "/", so "*.rs" matches Rust files at any depth
+//   - any of the above prefixed with "!" excludes the paths it matches
+// The explorer requests the folders it has open instead: there "" or "src/" only matches the
+// direct children of the folder, see `PathFilter::for_requested_folders`.
+// An empty list of rules (besides exclusions) matches every path. Paths use "/" as separator and
+// folders always end with "/", like the `root` given by `Tree::walk`.
+#[derive(Clone, Debug, Default)]
+pub struct PathFilter {
+    includes: Vec<PathRule>,
+    excludes: Vec<PathRule>,
+}
+
+#[derive(Clone, Debug)]
+enum PathRule {
+    Children(String),
+    // A path without a trailing "/", with everything within it
+    Path(String),
+    Recursive(String),
+    // The pattern and the folder before its first wildcard
+    Glob(Pattern, String),
+}
+
+const GLOB_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+fn has_wildcards(rule: &str) -> bool {
+    rule.contains(['*', '?', '['])
+}
+
+impl PathRule {
+    fn new(rule: &str) -> Result<Self, String> {
+        if !has_wildcards(rule) {
+            return match rule.trim_end_matches('/') {
+                "" => Ok(PathRule::Recursive(String::new())),
+                path => Ok(PathRule::Path(path.to_owned())),
+            };
+        }
+        if let Some(folder) = rule.strip_suffix("**") {
+            if (folder.is_empty() || folder.ends_with('/')) && !has_wildcards(folder) {
+                return Ok(PathRule::Recursive(folder.to_owned()));
+            }
+        }
+        match Pattern::new(rule) {
+            Ok(pattern) => {
+                // The folder holding everything before the first wildcard, used to prune subtrees
+                let literal = &rule[..rule.find(['*', '?', '[']).unwrap_or(rule.len())];
+                let prefix = &literal[..literal.rfind('/').map_or(0, |x| x + 1)];
+                Ok(PathRule::Glob(pattern, prefix.to_owned()))
+            }
+            Err(err) => Err(format!("Invalid path pattern {}: {}", rule, err)),
+        }
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        match self {
+            PathRule::Children(folder) => match path.rfind('/') {
+                Some(position) => path[..=position] == *folder,
+                None => folder.is_empty(),
+            },
+            PathRule::Path(x) => {
+                path.starts_with(x.as_str())
+                    && (path.len() == x.len() || path[x.len()..].starts_with('/'))
+            }
+            PathRule::Recursive(folder) => {
+                path.starts_with(folder.as_str()) && path.len() > folder.len()
+            }
+            PathRule::Glob(pattern, _) => pattern.matches_with(path, GLOB_OPTIONS),
+        }
+    }
+
+    fn may_match_within(&self, folder: &str) -> bool {
+        // Could anything within this folder (which ends with "/") be matched by this rule?
+        match self {
+            PathRule::Children(x) => x.starts_with(folder),
+            PathRule::Path(x) => self.matches(folder) || x.starts_with(folder),
+            PathRule::Recursive(x) | PathRule::Glob(_, x) => {
+                folder.starts_with(x.as_str()) || x.starts_with(folder)
+            }
+        }
+    }
+
+    fn matches_everything_within(&self, folder: &str) -> bool {
+        match self {
+            PathRule::Path(_) => self.matches(folder),
+            PathRule::Recursive(x) => folder.starts_with(x.as_str()),
+            _ => false,
+        }
+    }
+}
+
+impl PathFilter {
+    pub fn new(rules: &[&str]) -> Result<Self, String> {
+        let mut filter = PathFilter::default();
+        for rule in rules {
+            match rule.strip_prefix('!') {
+                Some(rule) => filter.excludes.push(PathRule::new(rule)?),
+                None => filter.includes.push(PathRule::new(rule)?),
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn for_requested_folders(requested_folders: &[&str]) -> Result<Self, String> {
+        // The explorer sends the folders it has open, when none are open we list nothing
+        // Folders like "" or "src/" list their direct children, other rules are pathspecs
+        if requested_folders.is_empty() {
+            return Ok(PathFilter::nothing());
+        }
+        let mut filter = PathFilter::default();
+        for rule in requested_folders {
+            match rule.strip_prefix('!') {
+                Some(rule) => filter.excludes.push(PathRule::new(rule)?),
+                None if rule.is_empty() || (rule.ends_with('/') && !has_wildcards(rule)) => {
+                    filter.includes.push(PathRule::Children(rule.to_string()))
+                }
+                None => filter.includes.push(PathRule::new(rule)?),
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn everything() -> Self {
+        PathFilter::default()
+    }
+
+    pub fn nothing() -> Self {
+        PathFilter {
+            includes: Vec::new(),
+            excludes: vec![PathRule::Recursive(String::new())],
+        }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        (self.includes.is_empty() || self.includes.iter().any(|x| x.matches(path)))
+            && !self.excludes.iter().any(|x| x.matches(path))
+    }
+
+    pub fn should_descend(&self, folder: &str) -> bool {
+        // Subtrees which can not contain any matching path are pruned from the walk
+        !self
+            .excludes
+            .iter()
+            .any(|x| x.matches_everything_within(folder))
+            && (self.includes.is_empty()
+                || self.includes.iter().any(|x| x.may_match_within(folder)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching(filter: &PathFilter, paths: &[&'static str]) -> Vec<&'static str> {
+        paths
+            .iter()
+            .copied()
+            .filter(|x| filter.matches(x))
+            .collect()
+    }
+
+    const PATHS: &[&str] = &[
+        "README.md",
+        "main.rs",
+        "src",
+        "src/lib.rs",
+        "src/gen",
+        "src/gen/parser.rs",
+        "src/gen/deep/lexer.rs",
+        "srcs/other.rs",
+        "docs/guide.md",
+    ];
+
+    #[test]
+    fn folder_rules_are_recursive() {
+        let filter = PathFilter::new(&["src"]).unwrap();
+        assert_eq!(
+            matching(&filter, PATHS),
+            vec![
+                "src",
+                "src/lib.rs",
+                "src/gen",
+                "src/gen/parser.rs",
+                "src/gen/deep/lexer.rs"
+            ]
+        );
+        // A trailing "/" makes no difference
+        let filter = PathFilter::new(&["src/"]).unwrap();
+        assert_eq!(matching(&filter, PATHS).len(), 5);
+        assert!(filter.should_descend("src/gen/deep/"));
+        assert!(!filter.should_descend("srcs/"));
+        assert!(!filter.should_descend("docs/"));
+
+        let filter = PathFilter::new(&["src/gen/parser.rs"]).unwrap();
+        assert_eq!(matching(&filter, PATHS), vec!["src/gen/parser.rs"]);
+        assert!(filter.should_descend("src/"));
+        assert!(!filter.should_descend("src/gen/deep/"));
+    }
+
+    #[test]
+    fn recursive_rules_leave_out_the_folder() {
+        let filter = PathFilter::new(&["src/**"]).unwrap();
+        assert_eq!(
+            matching(&filter, PATHS),
+            vec![
+                "src/lib.rs",
+                "src/gen",
+                "src/gen/parser.rs",
+                "src/gen/deep/lexer.rs"
+            ]
+        );
+        assert_eq!(matching(&PathFilter::new(&["**"]).unwrap(), PATHS), PATHS);
+        assert_eq!(matching(&PathFilter::new(&[""]).unwrap(), PATHS), PATHS);
+    }
+
+    #[test]
+    fn globs_match_at_any_depth() {
+        let filter = PathFilter::new(&["*.rs"]).unwrap();
+        assert_eq!(
+            matching(&filter, PATHS),
+            vec![
+                "main.rs",
+                "src/lib.rs",
+                "src/gen/parser.rs",
+                "src/gen/deep/lexer.rs",
+                "srcs/other.rs"
+            ]
+        );
+        assert!(filter.should_descend("src/gen/deep/"));
+
+        let filter = PathFilter::new(&["src/gen/*.rs"]).unwrap();
+        assert_eq!(
+            matching(&filter, PATHS),
+            vec!["src/gen/parser.rs", "src/gen/deep/lexer.rs"]
+        );
+        assert!(filter.should_descend("src/"));
+        assert!(!filter.should_descend("docs/"));
+
+        // Matching is case sensitive, like Git
+        assert!(matching(&PathFilter::new(&["*.MD"]).unwrap(), PATHS).is_empty());
+        assert!(PathFilter::new(&["src/[a"]).is_err());
+    }
+
+    #[test]
+    fn exclusions_are_recursive() {
+        let filter = PathFilter::new(&["src", "!src/gen/"]).unwrap();
+        assert_eq!(matching(&filter, PATHS), vec!["src", "src/lib.rs"]);
+        assert!(!filter.should_descend("src/gen/"));
+        assert!(!filter.should_descend("src/gen/deep/"));
+
+        // Without includes, everything else is matched
+        let filter = PathFilter::new(&["!*.rs", "!docs"]).unwrap();
+        assert_eq!(
+            matching(&filter, PATHS),
+            vec!["README.md", "src", "src/gen"]
+        );
+        assert!(filter.should_descend("src/"));
+        assert!(!filter.should_descend("docs/"));
+    }
+
+    #[test]
+    fn requested_folders_list_their_children() {
+        let filter = PathFilter::for_requested_folders(&["", "src/"]).unwrap();
+        assert_eq!(
+            matching(&filter, PATHS),
+            vec!["README.md", "main.rs", "src", "src/lib.rs", "src/gen"]
+        );
+        assert!(filter.should_descend("src/"));
+        assert!(!filter.should_descend("src/gen/"));
+
+        // Other rules are pathspecs
+        let filter = PathFilter::for_requested_folders(&["src/**", "!src/gen"]).unwrap();
+        assert_eq!(matching(&filter, PATHS), vec!["src/lib.rs"]);
+
+        let filter = PathFilter::for_requested_folders(&[]).unwrap();
+        assert!(matching(&filter, PATHS).is_empty());
+        assert!(!filter.should_descend(""));
+        assert_eq!(matching(&PathFilter::everything(), PATHS), PATHS);
+    }
+}
@@ -0,0 +1,195 @@
+use std::{path::Path, thread};
+
+use git2::Repository;
+
+use crate::object_cache::ObjectCache;
+
+/*
+Scanning the history for statistics does the same independent work for every commit, so we split
+the commits in contiguous index ranges and scan each range on its own thread. libgit2 repository
+handles can not be shared between threads, so each thread opens its own handle and keeps its own
+object cache. Results are merged back in the order of the given commits.
+ */
+pub fn scan_commits<T, F>(
+    repository_path: &Path,
+    commit_ids: &[String],
+    scan_commit: F,
+) -> Result<Vec<Result<T, String>>, String>
+where
+    T: Send,
+    F: Fn(&Repository, &mut ObjectCache, usize) -> Result<T, String> + Sync,
+{
+    if commit_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let thread_count = thread::available_parallelism()
+        .map_or(1, |x| x.get())
+        .min(commit_ids.len());
+    let chunk_size = commit_ids.len().div_ceil(thread_count);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..commit_ids.len())
+            .step_by(chunk_size)
+            .map(|start_index| {
+                let end_index = (start_index + chunk_size).min(commit_ids.len());
+                let scan_commit = &scan_commit;
+                scope.spawn(move || match Repository::open(repository_path) {
+                    Ok(repository) => {
+                        let mut object_cache = ObjectCache::new();
+                        Ok((start_index..end_index)
+                            .map(|index| scan_commit(&repository, &mut object_cache, index))
+                            .collect::<Vec<Result<T, String>>>())
+                    }
+                    Err(err) => Err(err.message().to_string()),
+                })
+            })
+            .collect();
+
+        let mut output: Vec<Result<T, String>> = Vec::new();
+        for handle in handles {
+            match handle.join() {
+                Ok(results) => output.extend(results?),
+                Err(_) => return Err("A history scanning thread panicked".to_owned()),
+            }
+        }
+        Ok(output)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use git2::{Oid, Signature, Time};
+
+    use crate::{
+        path_filter::PathFilter,
+        walker::{get_commit_details, get_file_hashes_for_paths_in_commit, load_all_commits},
+    };
+
+    fn make_repository(commits_count: usize) -> tempfile::TempDir {
+        // Each commit adds a file and changes a shared one, so every frame is different
+        let folder = tempfile::TempDir::new().unwrap();
+        let repository = Repository::init(folder.path()).unwrap();
+        let mut parent: Option<Oid> = None;
+        for index in 0..commits_count {
+            let mut builder = repository
+                .treebuilder(
+                    parent
+                        .map(|x| repository.find_commit(x).unwrap().tree().unwrap())
+                        .as_ref(),
+                )
+                .unwrap();
+            let added = repository.blob(format!("{}\n", index).as_bytes()).unwrap();
+            builder
+                .insert(format!("file-{}.txt", index), added, 0o100644)
+                .unwrap();
+            let shared = repository
+                .blob(format!("{}\n", index * 7).as_bytes())
+                .unwrap();
+            builder.insert("shared.txt", shared, 0o100644).unwrap();
+            let tree = repository.find_tree(builder.write().unwrap()).unwrap();
+            let signature = Signature::new(
+                "Fixture",
+                "fixture@example.com",
+                &Time::new(index as i64, 0),
+            )
+            .unwrap();
+            let parents: Vec<git2::Commit> = parent
+                .map(|x| repository.find_commit(x).unwrap())
+                .into_iter()
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            parent = Some(
+                repository
+                    .commit(
+                        Some("HEAD"),
+                        &signature,
+                        &signature,
+                        &format!("Commit {}", index),
+                        &tree,
+                        &parents,
+                    )
+                    .unwrap(),
+            );
+        }
+        folder
+    }
+
+    #[test]
+    fn parallel_scan_matches_a_sequential_walk() {
+        // More commits than threads, and not a multiple of the number of threads
+        let threads = thread::available_parallelism().map_or(1, |x| x.get());
+        let folder = make_repository(threads * 3 + 1);
+        let repository = Repository::open(folder.path()).unwrap();
+        let commit_ids: Vec<String> = load_all_commits(&repository)
+            .unwrap()
+            .iter()
+            .map(|x| x.get_id())
+            .collect();
+        assert_eq!(commit_ids.len(), threads * 3 + 1);
+
+        let scan_commit =
+            |repository: &Repository, object_cache: &mut ObjectCache, index: usize| {
+                let frame =
+                    get_commit_details(repository, commit_ids[index].as_str(), false, None, false)?;
+                let files = get_file_hashes_for_paths_in_commit(
+                    repository,
+                    commit_ids[index].as_str(),
+                    &PathFilter::everything(),
+                    object_cache,
+                )?;
+                Ok((
+                    frame.get_id(),
+                    frame.get_message().to_owned(),
+                    files
+                        .into_iter()
+                        .map(|x| (x.path, x.hash))
+                        .collect::<Vec<(String, String)>>(),
+                ))
+            };
+        let mut object_cache = ObjectCache::new();
+        let sequential: Vec<_> = (0..commit_ids.len())
+            .map(|index| scan_commit(&repository, &mut object_cache, index).unwrap())
+            .collect();
+        let parallel: Vec<_> = scan_commits(folder.path(), &commit_ids, scan_commit)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.unwrap())
+            .collect();
+        assert_eq!(parallel, sequential);
+        assert_eq!(parallel[0].1, "Commit 0");
+        assert_eq!(parallel[threads * 3].2.len(), threads * 3 + 2);
+    }
+
+    #[test]
+    fn errors_stay_at_the_index_of_their_commit() {
+        let folder = make_repository(5);
+        let commit_ids: Vec<String> = (0..5).map(|x| x.to_string()).collect();
+        let results = scan_commits(folder.path(), &commit_ids, |_, _, index| match index % 2 {
+            0 => Ok(index),
+            _ => Err(format!("Commit {} failed", index)),
+        })
+        .unwrap();
+        assert_eq!(
+            results,
+            vec![
+                Ok(0),
+                Err("Commit 1 failed".to_owned()),
+                Ok(2),
+                Err("Commit 3 failed".to_owned()),
+                Ok(4)
+            ]
+        );
+
+        assert!(scan_commits(folder.path(), &[], |_, _, index| Ok(index))
+            .unwrap()
+            .is_empty());
+        assert!(scan_commits(
+            &folder.path().join("missing"),
+            &commit_ids,
+            |_, _, index| Ok(index)
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashSet;
+
+use git2::{DiffDelta, ObjectType, Oid, Repository};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    diff::get_diff_with_first_parent,
+    object_cache::ObjectCache,
+    path_filter::PathFilter,
+    walker::{get_commit_tree, get_matching_tree_entries},
+};
+
+/*
+Pickaxe search is the equivalent of `git log -S` and `git log -G`. With `-S` we look for commits
+which change the number of occurrences of a string in a file. With `-G` we look for commits where
+an added or removed line matches a regular expression.
+ */
+pub enum Pickaxe {
+    Occurrences(String),
+    LineRegex(Regex),
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PickaxeMatch {
+    pub commit_id: String,
+    pub commit_index: usize,
+    pub paths: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GrepMatch {
+    pub path: String,
+    pub line_number: usize,
+    pub line: String,
+}
+
+impl Pickaxe {
+    pub fn new(pattern: &str, is_regex: bool) -> Result<Self, String> {
+        if pattern.is_empty() {
+            return Err("Search pattern is empty".to_owned());
+        }
+        if is_regex {
+            match Regex::new(pattern) {
+                Ok(regex) => Ok(Pickaxe::LineRegex(regex)),
+                Err(err) => Err(format!("Invalid regular expression: {}", err)),
+            }
+        } else {
+            Ok(Pickaxe::Occurrences(pattern.to_owned()))
+        }
+    }
+}
+
+fn matches_delta(path_filter: &PathFilter, delta: &DiffDelta) -> bool {
+    // Like a pathspec given to `git log -S`, a file moved into or out of the filtered paths counts
+    [delta.old_file().path(), delta.new_file().path()]
+        .iter()
+        .flatten()
+        .any(|x| path_filter.matches(&x.to_string_lossy()))
+}
+
+pub(crate) fn get_paths_matching_pickaxe(
+    repository: &Repository,
+    commit_id: &str,
+    pickaxe: &Pickaxe,
+    path_filter: &PathFilter,
+) -> Result<Vec<String>, String> {
+    // Find the files, changed in the given commit, that match our pickaxe
+    let commit = match Oid::from_str(commit_id).and_then(|oid| repository.find_commit(oid)) {
+        Ok(commit) => commit,
+        Err(_) => return Err("Could not parse the given revision specification".to_owned()),
+    };
+    // Like `git log -S` without `-m`, merges are left out: their changes against the first parent
+    // were already found in the commits of the merged branch
+    if commit.parent_count() > 1 {
+        return Ok(Vec::new());
+    }
+    let diff = get_diff_with_first_parent(repository, &commit)?;
+    let mut paths: Vec<String> = Vec::new();
+
+    match pickaxe {
+        Pickaxe::Occurrences(needle) => {
+            for delta in diff.deltas() {
+                let path = match delta.new_file().path().or(delta.old_file().path()) {
+                    Some(path) => path.to_string_lossy().into_owned(),
+                    None => continue,
+                };
+                if !matches_delta(path_filter, &delta) {
+                    continue;
+                }
+                let old_count =
+                    count_occurrences_in_blob(repository, delta.old_file().id(), needle);
+                let new_count =
+                    count_occurrences_in_blob(repository, delta.new_file().id(), needle);
+                if old_count != new_count {
+                    paths.push(path);
+                }
+            }
+        }
+        Pickaxe::LineRegex(regex) => {
+            let mut matched: HashSet<String> = HashSet::new();
+            diff.foreach(
+                &mut |_, _| true,
+                None,
+                None,
+                Some(&mut |delta, _, line| {
+                    // We only care about lines which were added or removed
+                    if line.origin() == '+' || line.origin() == '-' {
+                        if let Some(path) = delta.new_file().path().or(delta.old_file().path()) {
+                            let path = path.to_string_lossy().into_owned();
+                            if !matched.contains(&path)
+                                && matches_delta(path_filter, &delta)
+                                && regex.is_match(&String::from_utf8_lossy(line.content()))
+                            {
+                                paths.push(path.clone());
+                                matched.insert(path);
+                            }
+                        }
+                    }
+                    true
+                }),
+            )
+            .map_err(|err| format!("Could not read diff of commit: {}", err.message()))?;
+        }
+    }
+    Ok(paths)
+}
+
+pub(crate) fn grep_at_commit(
+    repository: &Repository,
+    commit_id: &str,
+    pattern: &str,
+    is_regex: bool,
+    path_globs: Vec<&str>,
+    object_cache: &mut ObjectCache,
+) -> Result<Vec<GrepMatch>, String> {
+    // Search the contents of all files in the tree of the given commit, like `git grep`
+    // Binary files are skipped and files can be limited to those matching the given path filter
+    let regex = match is_regex {
+        true => Regex::new(pattern),
+        false => Regex::new(&regex::escape(pattern)),
+    }
+    .map_err(|err| format!("Invalid regular expression: {}", err))?;
+    let path_filter = PathFilter::new(&path_globs)?;
+    let tree = get_commit_tree(repository, commit_id)?;
+
+    let mut output: Vec<GrepMatch> = Vec::new();
+    for entry in get_matching_tree_entries(repository, &tree, &path_filter, object_cache).iter() {
+        if entry.kind != Some(ObjectType::Blob) {
+            continue;
+        }
+        match repository.find_blob(entry.object_id) {
+            Ok(blob) if !blob.is_binary() => {
+                let contents = String::from_utf8_lossy(blob.content());
+                for (index, line) in contents.lines().enumerate() {
+                    if regex.is_match(line) {
+                        output.push(GrepMatch {
+                            path: entry.get_path(),
+                            line_number: index + 1,
+                            line: line.to_owned(),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(output)
+}
+
+fn count_occurrences_in_blob(repository: &Repository, object_id: Oid, needle: &str) -> usize {
+    // Missing side of a diff (added or deleted file) has a zero object id
+    if object_id.is_zero() {
+        return 0;
+    }
+    match repository.find_blob(object_id) {
+        Ok(blob) if !blob.is_binary() => String::from_utf8_lossy(blob.content())
+            .matches(needle)
+            .count(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,449 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::Arc,
+};
+
+use git2::{Delta, ObjectType, Oid, Repository, Tree};
+use serde::Serialize;
+use tree_sitter::{Language, Node, Parser};
+
+use crate::{
+    object_cache::ObjectCache,
+    path_filter::PathFilter,
+    walker::{get_commit_tree, get_matching_tree_entries},
+};
+
+/*
+Symbols are the functions, classes, structs (and similar definitions) which we extract from source
+files using tree-sitter. Symbols are extracted once per blob and cached by the blob's object id, so
+walking the timeline only parses files that have changed between frames.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    Function,
+    Class,
+    Struct,
+    Enum,
+    Trait,
+    Interface,
+    TypeAlias,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Symbol {
+    name: String,
+    kind: SymbolKind,
+    start_line: usize,
+    end_line: usize,
+    // Hash of the definition with its own name left out, used to detect renames
+    #[serde(skip)]
+    body_hash: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileSymbols {
+    path: String,
+    object_id: String,
+    symbols: Vec<Symbol>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SymbolLocation {
+    path: String,
+    kind: SymbolKind,
+    start_line: usize,
+    #[serde(skip)]
+    body_hash: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolEventKind {
+    Introduced,
+    Modified,
+    Moved,
+    Renamed,
+    Deleted,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SymbolEvent {
+    commit_id: String,
+    commit_index: usize,
+    event: SymbolEventKind,
+    locations: Vec<SymbolLocation>,
+    renamed_from: Option<String>,
+    renamed_to: Option<String>,
+}
+
+#[derive(Default)]
+pub struct SymbolIndex {
+    // The grammar comes from the extension, the same blob can be parsed as more than one language
+    symbols_by_blob: HashMap<(Oid, String), Arc<Vec<Symbol>>>,
+}
+
+#[derive(Clone, Copy)]
+enum SourceLanguage {
+    Rust,
+    Python,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+impl SourceLanguage {
+    fn from_path(path: &str) -> Option<Self> {
+        match Path::new(path).extension().and_then(|x| x.to_str()) {
+            Some("rs") => Some(SourceLanguage::Rust),
+            Some("py") => Some(SourceLanguage::Python),
+            Some("ts") | Some("mts") | Some("cts") => Some(SourceLanguage::TypeScript),
+            Some("tsx") => Some(SourceLanguage::Tsx),
+            Some("go") => Some(SourceLanguage::Go),
+            _ => None,
+        }
+    }
+
+    fn grammar(&self) -> Language {
+        match self {
+            SourceLanguage::Rust => tree_sitter_rust::language(),
+            SourceLanguage::Python => tree_sitter_python::language(),
+            SourceLanguage::TypeScript => tree_sitter_typescript::language_typescript(),
+            SourceLanguage::Tsx => tree_sitter_typescript::language_tsx(),
+            SourceLanguage::Go => tree_sitter_go::language(),
+        }
+    }
+
+    fn symbol_kind(&self, node: &Node) -> Option<SymbolKind> {
+        // Map the syntax node kinds of each grammar to our symbol kinds
+        match self {
+            SourceLanguage::Rust => match node.kind() {
+                "function_item" | "function_signature_item" => Some(SymbolKind::Function),
+                "struct_item" | "union_item" => Some(SymbolKind::Struct),
+                "enum_item" => Some(SymbolKind::Enum),
+                "trait_item" => Some(SymbolKind::Trait),
+                "type_item" => Some(SymbolKind::TypeAlias),
+                _ => None,
+            },
+            SourceLanguage::Python => match node.kind() {
+                "function_definition" => Some(SymbolKind::Function),
+                "class_definition" => Some(SymbolKind::Class),
+                _ => None,
+            },
+            SourceLanguage::TypeScript | SourceLanguage::Tsx => match node.kind() {
+                "function_declaration" | "generator_function_declaration" | "method_definition" => {
+                    Some(SymbolKind::Function)
+                }
+                "class_declaration" | "abstract_class_declaration" => Some(SymbolKind::Class),
+                "interface_declaration" => Some(SymbolKind::Interface),
+                "enum_declaration" => Some(SymbolKind::Enum),
+                "type_alias_declaration" => Some(SymbolKind::TypeAlias),
+                _ => None,
+            },
+            SourceLanguage::Go => match node.kind() {
+                "function_declaration" | "method_declaration" => Some(SymbolKind::Function),
+                "type_spec" => match node.child_by_field_name("type").map(|x| x.kind()) {
+                    Some("struct_type") => Some(SymbolKind::Struct),
+                    Some("interface_type") => Some(SymbolKind::Interface),
+                    _ => Some(SymbolKind::TypeAlias),
+                },
+                _ => None,
+            },
+        }
+    }
+}
+
+pub fn is_supported_path(path: &str) -> bool {
+    SourceLanguage::from_path(path).is_some()
+}
+
+pub fn extract_symbols(path: &str, source: &[u8]) -> Vec<Symbol> {
+    // Parse the source with the grammar matching the file extension and collect all definitions
+    let language = match SourceLanguage::from_path(path) {
+        Some(language) => language,
+        None => return Vec::new(),
+    };
+    let mut parser = Parser::new();
+    if parser.set_language(language.grammar()).is_err() {
+        return Vec::new();
+    }
+    let syntax_tree = match parser.parse(source, None) {
+        Some(syntax_tree) => syntax_tree,
+        None => return Vec::new(),
+    };
+
+    let mut output: Vec<Symbol> = Vec::new();
+    let mut cursor = syntax_tree.walk();
+    let mut visited_children = false;
+    loop {
+        let node = cursor.node();
+        if !visited_children {
+            if let Some(kind) = language.symbol_kind(&node) {
+                if let Some(name_node) = node.child_by_field_name("name") {
+                    if let Ok(name) = name_node.utf8_text(source) {
+                        output.push(Symbol {
+                            name: name.to_owned(),
+                            kind,
+                            start_line: node.start_position().row + 1,
+                            end_line: node.end_position().row + 1,
+                            body_hash: get_body_hash(&node, &name_node, source),
+                        });
+                    }
+                }
+            }
+            if cursor.goto_first_child() {
+                continue;
+            }
+        }
+        if cursor.goto_next_sibling() {
+            visited_children = false;
+        } else if cursor.goto_parent() {
+            visited_children = true;
+        } else {
+            break;
+        }
+    }
+    output
+}
+
+fn get_body_hash(node: &Node, name_node: &Node, source: &[u8]) -> u64 {
+    // Hash of the definition with its own name left out, so a renamed definition hashes the same
+    // The name is left out wherever it is the same kind of token, like in recursive calls, but
+    // not where it is a field or method of something else
+    let name = name_node.utf8_text(source).unwrap_or("");
+    let mut hasher = DefaultHasher::new();
+    let mut position = node.start_byte();
+    let mut cursor = node.walk();
+    let mut visited_children = false;
+    loop {
+        let token = cursor.node();
+        if !visited_children {
+            if token.child_count() == 0
+                && token.kind() == name_node.kind()
+                && token.utf8_text(source) == Ok(name)
+            {
+                source[position..token.start_byte()].hash(&mut hasher);
+                position = token.end_byte();
+            }
+            if cursor.goto_first_child() {
+                continue;
+            }
+        }
+        if cursor.goto_next_sibling() {
+            visited_children = false;
+        } else if cursor.goto_parent() {
+            visited_children = true;
+        } else {
+            break;
+        }
+    }
+    source[position..node.end_byte()].hash(&mut hasher);
+    hasher.finish()
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        SymbolIndex::default()
+    }
+
+    fn get_symbols_for_blob(
+        &mut self,
+        repository: &Repository,
+        path: &str,
+        object_id: Oid,
+    ) -> Arc<Vec<Symbol>> {
+        let key = (
+            object_id,
+            Path::new(path)
+                .extension()
+                .map_or(String::new(), |x| x.to_string_lossy().into_owned()),
+        );
+        if let Some(symbols) = self.symbols_by_blob.get(&key) {
+            return symbols.clone();
+        }
+        let symbols = match repository.find_blob(object_id) {
+            Ok(blob) if !blob.is_binary() => extract_symbols(path, blob.content()),
+            _ => Vec::new(),
+        };
+        let symbols = Arc::new(symbols);
+        self.symbols_by_blob.insert(key, symbols.clone());
+        symbols
+    }
+
+    pub(crate) fn get_symbols_at_commit(
+        &mut self,
+        repository: &Repository,
+        commit_id: &str,
+        path_filter: &PathFilter,
+        object_cache: &mut ObjectCache,
+    ) -> Result<Vec<FileSymbols>, String> {
+        // Get all symbols of all supported files in the tree of the given commit
+        let tree = get_commit_tree(repository, commit_id)?;
+        let mut output: Vec<FileSymbols> = Vec::new();
+        for entry in get_matching_tree_entries(repository, &tree, path_filter, object_cache).iter()
+        {
+            let path = entry.get_path();
+            if entry.kind == Some(ObjectType::Blob) && is_supported_path(&path) {
+                output.push(FileSymbols {
+                    symbols: self
+                        .get_symbols_for_blob(repository, &path, entry.object_id)
+                        .to_vec(),
+                    object_id: entry.object_id.to_string(),
+                    path,
+                });
+            }
+        }
+        Ok(output)
+    }
+
+    pub fn get_symbol_timeline(
+        &mut self,
+        repository: &Repository,
+        commit_ids: &[String],
+        name: &str,
+    ) -> Result<Vec<SymbolEvent>, String> {
+        // Follow a symbol by name across the given frames and record when it was introduced,
+        // modified, moved, renamed or deleted
+        // Between consecutive frames we only parse the files that changed
+        let mut output: Vec<SymbolEvent> = Vec::new();
+        let mut locations_by_path: HashMap<String, Vec<SymbolLocation>> = HashMap::new();
+        let mut previous_locations: Vec<SymbolLocation> = Vec::new();
+        let mut previous_tree: Option<Tree> = None;
+
+        for (index, commit_id) in commit_ids.iter().enumerate() {
+            let tree = get_commit_tree(repository, commit_id)?;
+            let diff = repository
+                .diff_tree_to_tree(previous_tree.as_ref(), Some(&tree), None)
+                .map_err(|err| format!("Could not diff commit: {}", err.message()))?;
+
+            // Symbols which disappeared from, or appeared in, the changed files of this frame
+            let mut removed: Vec<Symbol> = Vec::new();
+            let mut added: Vec<Symbol> = Vec::new();
+            for delta in diff.deltas() {
+                let old_path = delta.old_file().path().map(|x| x.to_string_lossy());
+                let new_path = delta.new_file().path().map(|x| x.to_string_lossy());
+                let old_symbols = match (&old_path, delta.status()) {
+                    (Some(path), status) if status != Delta::Added && is_supported_path(path) => {
+                        self.get_symbols_for_blob(repository, path, delta.old_file().id())
+                    }
+                    _ => Arc::new(Vec::new()),
+                };
+                let new_symbols = match (&new_path, delta.status()) {
+                    (Some(path), status) if status != Delta::Deleted && is_supported_path(path) => {
+                        self.get_symbols_for_blob(repository, path, delta.new_file().id())
+                    }
+                    _ => Arc::new(Vec::new()),
+                };
+                for symbol in old_symbols.iter() {
+                    if !new_symbols.iter().any(|x| x.name == symbol.name) {
+                        removed.push(symbol.clone());
+                    }
+                }
+                for symbol in new_symbols.iter() {
+                    if !old_symbols.iter().any(|x| x.name == symbol.name) {
+                        added.push(symbol.clone());
+                    }
+                }
+
+                if let Some(path) = &old_path {
+                    locations_by_path.remove(path.as_ref());
+                }
+                if let Some(path) = &new_path {
+                    let locations: Vec<SymbolLocation> = new_symbols
+                        .iter()
+                        .filter(|x| x.name == name)
+                        .map(|x| SymbolLocation {
+                            path: path.to_string(),
+                            kind: x.kind,
+                            start_line: x.start_line,
+                            body_hash: x.body_hash,
+                        })
+                        .collect();
+                    if !locations.is_empty() {
+                        locations_by_path.insert(path.to_string(), locations);
+                    }
+                }
+            }
+
+            let mut locations: Vec<SymbolLocation> =
+                locations_by_path.values().flatten().cloned().collect();
+            locations.sort_by(|a, b| (&a.path, a.start_line).cmp(&(&b.path, b.start_line)));
+
+            let event = match (previous_locations.is_empty(), locations.is_empty()) {
+                (true, false) => {
+                    let renamed_from = find_renamed_symbol(&removed, &locations, name);
+                    Some(match renamed_from {
+                        Some(_) => (SymbolEventKind::Renamed, renamed_from, None),
+                        None => (SymbolEventKind::Introduced, None, None),
+                    })
+                }
+                (false, true) => {
+                    let renamed_to = find_renamed_symbol(&added, &previous_locations, name);
+                    Some(match renamed_to {
+                        Some(_) => (SymbolEventKind::Renamed, None, renamed_to),
+                        None => (SymbolEventKind::Deleted, None, None),
+                    })
+                }
+                (false, false) => {
+                    let paths = |x: &Vec<SymbolLocation>| {
+                        x.iter().map(|y| y.path.clone()).collect::<Vec<String>>()
+                    };
+                    // Lines added or removed above a definition move it without changing it
+                    let definitions = |x: &Vec<SymbolLocation>| {
+                        x.iter()
+                            .map(|y| (y.path.clone(), y.kind, y.body_hash))
+                            .collect::<Vec<_>>()
+                    };
+                    if paths(&previous_locations) != paths(&locations) {
+                        Some((SymbolEventKind::Moved, None, None))
+                    } else if definitions(&previous_locations) != definitions(&locations) {
+                        Some((SymbolEventKind::Modified, None, None))
+                    } else {
+                        None
+                    }
+                }
+                (true, true) => None,
+            };
+            if let Some((event, renamed_from, renamed_to)) = event {
+                output.push(SymbolEvent {
+                    commit_id: commit_id.clone(),
+                    commit_index: index,
+                    event,
+                    locations: match event {
+                        SymbolEventKind::Deleted | SymbolEventKind::Renamed
+                            if locations.is_empty() =>
+                        {
+                            previous_locations.clone()
+                        }
+                        _ => locations.clone(),
+                    },
+                    renamed_from,
+                    renamed_to,
+                });
+            }
+
+            previous_locations = locations;
+            previous_tree = Some(tree);
+        }
+        Ok(output)
+    }
+}
+
+fn find_renamed_symbol(
+    candidates: &[Symbol],
+    locations: &[SymbolLocation],
+    name: &str,
+) -> Option<String> {
+    // A rename is a symbol with another name but of the same kind and with an identical body
+    candidates
+        .iter()
+        .find(|candidate| {
+            candidate.name != name
+                && locations
+                    .iter()
+                    .any(|x| x.kind == candidate.kind && x.body_hash == candidate.body_hash)
+        })
+        .map(|x| x.name.clone())
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use git2::{Oid, Repository};
+use serde::Serialize;
+
+/*
+Tags mark releases on the timeline. Lightweight tags are only a name for a commit, annotated tags
+also have a tagger, a date and a message. Both are resolved to the commit they point at (through
+tags of tags) and to its index in the cached timeline.
+ */
+#[derive(Clone, Debug, Serialize)]
+pub struct Tag {
+    pub name: String,
+    pub commit_id: String,
+    // None when the tagged commit is not in the timeline, like a tag on another branch
+    pub commit_index: Option<usize>,
+    // Time of the tagged commit
+    pub time: i64,
+    pub is_annotated: bool,
+    // Only annotated tags have these
+    pub tagger: Option<String>,
+    pub tagger_email: Option<String>,
+    pub tag_time: Option<i64>,
+    pub message: Option<String>,
+}
+
+pub fn get_tags(
+    repository: &Repository,
+    commit_indices: &HashMap<String, usize>,
+) -> Result<Vec<Tag>, String> {
+    // Tags of the repository pointing to commits, oldest first
+    let tag_names = match repository.tag_names(None) {
+        Ok(tag_names) => tag_names,
+        Err(err) => return Err(format!("Could not read tags: {}", err.message())),
+    };
+    let mut output: Vec<Tag> = Vec::new();
+    for name in tag_names.iter().flatten() {
+        // The object the tag ref points at directly is the annotated tag, if there is one
+        let object = match repository
+            .find_reference(&format!("refs/tags/{}", name))
+            .and_then(|x| x.resolve())
+            .and_then(|x| repository.find_object(x.target().unwrap_or_else(Oid::zero), None))
+        {
+            Ok(object) => object,
+            Err(_) => continue,
+        };
+        let commit = match object.peel_to_commit() {
+            Ok(commit) => commit,
+            // Tags of trees or blobs are not releases
+            Err(_) => continue,
+        };
+        let commit_id = commit.id().to_string();
+        let mut tag = Tag {
+            name: name.to_owned(),
+            commit_index: commit_indices.get(&commit_id).copied(),
+            commit_id,
+            time: commit.time().seconds(),
+            is_annotated: false,
+            tagger: None,
+            tagger_email: None,
+            tag_time: None,
+            message: None,
+        };
+        if let Some(annotated_tag) = object.as_tag() {
+            tag.is_annotated = true;
+            tag.message = annotated_tag
+                .message_bytes()
+                .map(|x| String::from_utf8_lossy(x).into_owned());
+            // Very old tags may have been written without a tagger
+            if let Some(tagger) = annotated_tag.tagger() {
+                tag.tagger = Some(String::from_utf8_lossy(tagger.name_bytes()).into_owned());
+                tag.tagger_email = Some(String::from_utf8_lossy(tagger.email_bytes()).into_owned());
+                tag.tag_time = Some(tagger.when().seconds());
+            }
+        }
+        output.push(tag);
+    }
+    output.sort_by(|a, b| a.time.cmp(&b.time).then(a.name.cmp(&b.name)));
+    Ok(output)
+}
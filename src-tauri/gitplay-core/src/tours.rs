@@ -0,0 +1,239 @@
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use git2::{ObjectType, Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::object_cache::count_lines;
+
+/*
+A tour is a recorded walk through the history of a repository. Each step points at a commit and
+describes what to look at in that frame: the folders that are open, a file (optionally a range of
+its lines) and a note in markdown. Playing the tour moves the timeline from step to step.
+
+Tours are JSON files. By default they live in .gitplay/tours in the working directory, so they can
+be committed with the repository, but a tour can be loaded from and saved to any path.
+ */
+
+const TOUR_FILE_VERSION: usize = 1;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct OpenedFile {
+    pub path: String,
+    // Lines are numbered from 1, the range includes both ends
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct TourStep {
+    pub commit_id: String,
+    // Folders like "src/" that are expanded in the file tree
+    #[serde(default)]
+    pub open_folders: Vec<String>,
+    #[serde(default)]
+    pub opened_file: Option<OpenedFile>,
+    // Markdown
+    #[serde(default)]
+    pub note: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Tour {
+    pub version: usize,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<TourStep>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TourFile {
+    pub file_path: String,
+    pub name: String,
+    pub steps_count: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TourProblem {
+    pub step_index: usize,
+    pub message: String,
+}
+
+/*
+ActiveTour is the tour that is being recorded or played, with the file it is saved to and the
+step the timeline is at.
+ */
+#[derive(Clone, Debug, Serialize)]
+pub struct ActiveTour {
+    pub file_path: String,
+    pub tour: Tour,
+    pub current_step: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TourStepView {
+    pub step_index: usize,
+    pub steps_count: usize,
+    pub step: TourStep,
+    // Index of the commit in the cached timeline, None if it is not part of the timeline
+    pub commit_index: Option<usize>,
+}
+
+impl Tour {
+    pub fn new(name: &str) -> Self {
+        Tour {
+            version: TOUR_FILE_VERSION,
+            name: name.to_owned(),
+            description: String::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn load(file_path: &Path) -> Result<Self, String> {
+        let contents = match fs::read_to_string(file_path) {
+            Ok(contents) => contents,
+            Err(err) => return Err(format!("Could not read {}: {}", file_path.display(), err)),
+        };
+        let tour: Tour = match serde_json::from_str(&contents) {
+            Ok(tour) => tour,
+            Err(err) => return Err(format!("{} is not a tour: {}", file_path.display(), err)),
+        };
+        if tour.version > TOUR_FILE_VERSION {
+            return Err(format!(
+                "{} needs a newer version of gitplay (tour version {})",
+                file_path.display(),
+                tour.version
+            ));
+        }
+        Ok(tour)
+    }
+
+    pub fn save(&self, file_path: &Path) -> Result<(), String> {
+        if let Some(folder) = file_path.parent() {
+            if let Err(err) = fs::create_dir_all(folder) {
+                return Err(format!("Could not create {}: {}", folder.display(), err));
+            }
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        match fs::write(file_path, json + "\n") {
+            Ok(_) => Ok(()),
+            Err(err) => Err(format!("Could not write {}: {}", file_path.display(), err)),
+        }
+    }
+}
+
+pub fn get_tours_folder(repository: &Repository) -> PathBuf {
+    // Bare repositories have no working directory, their tours are kept in the Git directory
+    match repository.workdir() {
+        Some(workdir) => workdir.join(".gitplay").join("tours"),
+        None => repository.path().join("gitplay").join("tours"),
+    }
+}
+
+pub fn get_default_tour_path(repository: &Repository, name: &str) -> PathBuf {
+    // File name from the name of the tour, like "The great refactor" to the-great-refactor.json
+    let mut slug = String::new();
+    for character in name.trim().to_lowercase().chars() {
+        if character.is_alphanumeric() {
+            slug.push(character);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    let slug = if slug.is_empty() { "tour" } else { slug };
+    get_tours_folder(repository).join(format!("{}.json", slug))
+}
+
+pub fn list_tours(repository: &Repository) -> Result<Vec<TourFile>, String> {
+    // Tours in the default folder of the repository, files that are not tours are skipped
+    let folder = get_tours_folder(repository);
+    let entries = match fs::read_dir(&folder) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut output: Vec<TourFile> = Vec::new();
+    for entry in entries.flatten() {
+        let file_path = entry.path();
+        if file_path.extension() != Some(OsStr::new("json")) {
+            continue;
+        }
+        if let Ok(tour) = Tour::load(&file_path) {
+            output.push(TourFile {
+                file_path: file_path.to_string_lossy().into_owned(),
+                name: tour.name,
+                steps_count: tour.steps.len(),
+            });
+        }
+    }
+    output.sort_by(|a, b| a.name.cmp(&b.name).then(a.file_path.cmp(&b.file_path)));
+    Ok(output)
+}
+
+pub fn validate_tour(repository: &Repository, tour: &Tour) -> Vec<TourProblem> {
+    // Check that every step still points at things that exist: its commit (history may have been
+    // rewritten since the tour was recorded), the open folders and the opened file and lines
+    let mut output: Vec<TourProblem> = Vec::new();
+    for (step_index, step) in tour.steps.iter().enumerate() {
+        let mut problem = |message: String| {
+            output.push(TourProblem {
+                step_index,
+                message,
+            })
+        };
+        let tree = match Oid::from_str(&step.commit_id)
+            .and_then(|x| repository.find_commit(x))
+            .and_then(|x| x.tree())
+        {
+            Ok(tree) => tree,
+            Err(_) => {
+                problem(format!("Commit {} does not exist", step.commit_id));
+                continue;
+            }
+        };
+        for folder in &step.open_folders {
+            let folder_path = folder.trim_end_matches('/');
+            if folder_path.is_empty() {
+                continue;
+            }
+            match tree.get_path(Path::new(folder_path)) {
+                Ok(entry) if entry.kind() == Some(ObjectType::Tree) => {}
+                _ => problem(format!("Folder {} does not exist at this commit", folder)),
+            }
+        }
+        if let Some(opened_file) = &step.opened_file {
+            let blob = match tree
+                .get_path(Path::new(&opened_file.path))
+                .and_then(|x| repository.find_blob(x.id()))
+            {
+                Ok(blob) => blob,
+                Err(_) => {
+                    problem(format!(
+                        "File {} does not exist at this commit",
+                        opened_file.path
+                    ));
+                    continue;
+                }
+            };
+            let line_count = count_lines(blob.content());
+            let start_line = opened_file.start_line.unwrap_or(1);
+            let end_line = opened_file.end_line.unwrap_or(start_line);
+            if start_line == 0 || start_line > end_line {
+                problem(format!(
+                    "Lines {} to {} are not a valid range",
+                    start_line, end_line
+                ));
+            } else if opened_file.start_line.is_some() && end_line > line_count {
+                problem(format!(
+                    "File {} has only {} lines at this commit",
+                    opened_file.path, line_count
+                ));
+            }
+        }
+    }
+    output
+}
@@ -0,0 +1,600 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use git2::{Commit, ErrorCode, ObjectType, Oid, ReferenceType, Repository, Sort, Tree};
+use serde::Serialize;
+
+use crate::{
+    notes::{get_commit_notes, CommitNote},
+    object_cache::{ObjectCache, TreeEntry},
+    path_filter::PathFilter,
+};
+
+/// A single commit in the timeline of the Git repository.
+///
+/// Each frame is like a frame in a movie that the user can pause at. Each frame has its file
+/// structure and parents.
+#[derive(Clone, Debug, Serialize)]
+pub struct CommitFrame {
+    commit_id: String,
+    commit_message: String,
+    author: String,
+    time: i64,
+    file_structure: Option<FileTree>,
+    parents: Vec<String>,
+    // Git notes from the notes refs configured for the repository, empty unless requested
+    notes: Vec<CommitNote>,
+    // Refs pointing at this commit, set for the commits of the cached timeline
+    decorations: Vec<Decoration>,
+}
+
+/// The short form of a [`CommitFrame`] listed in the timeline, with its index in it.
+#[derive(Clone, Debug, Serialize)]
+pub struct CommitSummary {
+    pub commit_index: usize,
+    pub commit_id: String,
+    pub commit_message: String,
+    pub author: String,
+    pub time: i64,
+    pub decorations: Vec<Decoration>,
+}
+
+/// The kind of ref of a [`Decoration`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefKind {
+    /// Only when HEAD is detached, otherwise the branch it points at is marked
+    Head,
+    Branch,
+    RemoteBranch,
+    Tag,
+}
+
+/// A ref pointing at a commit, like `git log --decorate` shows them:
+/// "HEAD -> main, origin/main, tag: v1.0".
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct Decoration {
+    /// Short name, like "main", "origin/main" or "v1.0"
+    pub name: String,
+    pub kind: RefKind,
+    /// Whether HEAD points at this branch
+    pub is_head: bool,
+}
+
+/// The listing of a commit's tree, as requested by the user: the blobs of the folders that were
+/// asked for, each with its size, file count and optionally line count.
+#[derive(Clone, Debug, Serialize)]
+pub struct FileTree {
+    object_id: String,
+    blobs: Vec<FileBlob>,
+}
+
+pub(crate) struct FileHashByPath {
+    pub path: String,
+    pub hash: String,
+}
+
+/// A file or directory of a [`FileTree`].
+#[derive(Clone, Debug, Serialize)]
+pub struct FileBlob {
+    object_id: String,
+    // Folder containing this file or directory, like "src/" (empty at the root of the tree)
+    path: String,
+    name: String,
+    is_directory: bool,
+    // For directories these are aggregated over all files within, recursively
+    size: usize,
+    file_count: usize,
+    line_count: Option<usize>,
+}
+
+impl CommitFrame {
+    /// The commit id and full message.
+    pub fn get_summary(&self) -> (String, String) {
+        (self.commit_id.clone(), self.commit_message.clone())
+    }
+
+    /// The commit id, or the revision the frame was loaded from, like "HEAD".
+    pub fn get_id(&self) -> String {
+        self.commit_id.clone()
+    }
+
+    /// Commit time in seconds since the Unix epoch.
+    pub fn get_time(&self) -> i64 {
+        self.time
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.commit_message
+    }
+
+    pub fn get_author(&self) -> &str {
+        &self.author
+    }
+
+    /// Ids of the parent commits, the first parent first.
+    pub fn get_parents(&self) -> &[String] {
+        &self.parents
+    }
+
+    /// Only present when the frame was loaded with its file tree.
+    pub fn get_file_structure(&self) -> Option<&FileTree> {
+        self.file_structure.as_ref()
+    }
+
+    /// Refs pointing at this commit, set for the commits of the cached timeline.
+    pub fn get_decorations(&self) -> &[Decoration] {
+        &self.decorations
+    }
+
+    pub fn set_decorations(&mut self, decorations: Vec<Decoration>) {
+        self.decorations = decorations;
+    }
+
+    /// Git notes read with [`CommitFrame::read_notes`], empty otherwise.
+    pub fn get_notes(&self) -> &[CommitNote] {
+        &self.notes
+    }
+
+    /// Read the notes of this commit from the given notes refs, in their order.
+    pub fn read_notes(&mut self, repository: &Repository, notes_refs: &[String]) {
+        self.notes = match repository
+            .revparse_single(&self.commit_id)
+            .and_then(|x| x.peel_to_commit())
+        {
+            Ok(commit) => get_commit_notes(repository, commit.id(), notes_refs),
+            Err(_) => Vec::new(),
+        };
+    }
+
+    /// The summary of this frame at the given index of the timeline.
+    pub fn to_summary(&self, commit_index: usize) -> CommitSummary {
+        CommitSummary {
+            commit_index,
+            commit_id: self.commit_id.clone(),
+            // Only the first line of the message, like `git log --oneline`
+            commit_message: self.commit_message.lines().next().unwrap_or("").to_owned(),
+            author: self.author.clone(),
+            time: self.time,
+            decorations: self.decorations.clone(),
+        }
+    }
+}
+
+impl FileTree {
+    /// Id of the tree object of the commit.
+    pub fn get_object_id(&self) -> &str {
+        &self.object_id
+    }
+
+    /// Files and directories of the requested folders.
+    pub fn get_blobs(&self) -> &[FileBlob] {
+        &self.blobs
+    }
+}
+
+impl FileBlob {
+    /// Id of the blob, or of the tree for a directory.
+    pub fn get_object_id(&self) -> &str {
+        &self.object_id
+    }
+
+    /// Folder containing this file or directory, like "src/" (empty at the root of the tree).
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.is_directory
+    }
+
+    /// Size in bytes, of all files within for a directory.
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    /// 1 for a file, the number of files within for a directory.
+    pub fn get_file_count(&self) -> usize {
+        self.file_count
+    }
+
+    /// Only present when line counts were requested. Binary files have no lines.
+    pub fn get_line_count(&self) -> Option<usize> {
+        self.line_count
+    }
+}
+
+/// Names of the local branches of the repository.
+pub fn get_all_branch_names(repository: &Repository) -> Vec<String> {
+    let mut output: Vec<String> = Vec::new();
+    if let Ok(branches) = repository.branches(None) {
+        for (branch, _) in branches.flatten() {
+            if let Ok(Some(name)) = branch.name() {
+                output.push(name.to_string());
+            }
+        }
+    }
+    output
+}
+
+/// Refs of the repository by the id of the commit they point at.
+pub fn get_decorations(repository: &Repository) -> HashMap<String, Vec<Decoration>> {
+    // Branches, remote branches and tags by the id of the commit they point at
+    // Symbolic refs like origin/HEAD are skipped, their target is already listed
+    let head_name = match repository.head() {
+        Ok(head) if repository.head_detached().unwrap_or(false) => {
+            head.target().map(|x| (x.to_string(), None))
+        }
+        Ok(head) => head
+            .target()
+            .map(|x| (x.to_string(), head.name().map(|x| x.to_owned()))),
+        Err(_) => None,
+    };
+    let mut output: HashMap<String, Vec<Decoration>> = HashMap::new();
+    if let Some((commit_id, None)) = &head_name {
+        output
+            .entry(commit_id.clone())
+            .or_default()
+            .push(Decoration {
+                name: "HEAD".to_owned(),
+                kind: RefKind::Head,
+                is_head: true,
+            });
+    }
+    if let Ok(references) = repository.references() {
+        for reference in references.flatten() {
+            if reference.kind() != Some(ReferenceType::Direct) {
+                continue;
+            }
+            let (full_name, name) = match (reference.name(), reference.shorthand()) {
+                (Some(full_name), Some(name)) => (full_name, name),
+                _ => continue,
+            };
+            let kind = if full_name.starts_with("refs/heads/") {
+                RefKind::Branch
+            } else if full_name.starts_with("refs/remotes/") {
+                RefKind::RemoteBranch
+            } else if full_name.starts_with("refs/tags/") {
+                RefKind::Tag
+            } else {
+                continue;
+            };
+            let commit_id = match reference.peel_to_commit() {
+                Ok(commit) => commit.id().to_string(),
+                Err(_) => continue,
+            };
+            let is_head = matches!(&head_name, Some((_, Some(head_ref))) if head_ref == full_name);
+            output.entry(commit_id).or_default().push(Decoration {
+                name: name.to_owned(),
+                kind,
+                is_head,
+            });
+        }
+    }
+    for decorations in output.values_mut() {
+        decorations.sort_by(|a, b| {
+            b.is_head
+                .cmp(&a.is_head)
+                .then(a.kind.cmp(&b.kind))
+                .then(a.name.cmp(&b.name))
+        });
+    }
+    output
+}
+
+/// The commits reachable from HEAD, oldest first, without their file trees.
+pub fn load_all_commits(repository: &Repository) -> Result<Vec<CommitFrame>, String> {
+    // We use libgit2 to walk the Git commit log
+    // We extract each commit and make our own data structure, CommitFrame, from the commit data
+    let walk = repository.revwalk();
+    let mut output: Vec<CommitFrame> = Vec::new();
+
+    match walk {
+        Ok(mut walkable) => {
+            // A repository without commits has an unborn HEAD, so its timeline is empty
+            if let Err(err) = repository.head() {
+                if err.code() == ErrorCode::UnbornBranch {
+                    return Ok(output);
+                }
+            }
+            if let Err(err) = walkable.push_head() {
+                return Err(format!("Could not find HEAD: {}", err.message()));
+            }
+            walkable
+                .set_sorting(Sort::TOPOLOGICAL | Sort::TIME | Sort::REVERSE)
+                .unwrap();
+
+            for commit in walkable.flatten() {
+                if let Ok(commit_details) =
+                    get_commit_details(repository, &commit.to_string(), false, None, false)
+                {
+                    output.push(commit_details);
+                }
+            }
+            Ok(output)
+        }
+        Err(x) => Err(format!("Could not walk repository: {}", x.message())),
+    }
+}
+
+/// The commit of a revision like "HEAD" or a commit id.
+///
+/// With `with_file_tree`, the frame lists the files and directories of the requested folders
+/// (like "" for the root and "src/"), or of the whole tree when none are given. Line counts read
+/// the contents of every file listed, including all files within directories, so they are only
+/// counted with `with_line_counts`.
+pub fn get_commit_details(
+    repository: &Repository,
+    git_spec: &str,
+    with_file_tree: bool,
+    requested_folders: Option<Vec<&str>>,
+    with_line_counts: bool,
+) -> Result<CommitFrame, String> {
+    get_commit_details_with_cache(
+        repository,
+        git_spec,
+        with_file_tree,
+        requested_folders,
+        with_line_counts,
+        &mut ObjectCache::new(),
+    )
+}
+
+pub(crate) fn get_commit_details_with_cache(
+    repository: &Repository,
+    git_spec: &str,
+    with_file_tree: bool,
+    requested_folders: Option<Vec<&str>>,
+    with_line_counts: bool,
+    object_cache: &mut ObjectCache,
+) -> Result<CommitFrame, String> {
+    // Get details for a single commit as our own data structure, CommitFrame
+    // Sizes in the file tree are read through the object cache, shared between calls
+    match repository.revparse_single(git_spec) {
+        Ok(tree_obj) => match tree_obj.kind() {
+            Some(ObjectType::Commit) => match tree_obj.as_commit() {
+                Some(commit) => {
+                    let mut frame = CommitFrame {
+                        commit_id: git_spec.to_owned(),
+                        parents: get_commit_parents(commit),
+                        // Messages are not required to be UTF-8, like those written with a legacy
+                        // i18n.commitEncoding
+                        commit_message: String::from_utf8_lossy(commit.message_bytes())
+                            .into_owned(),
+                        author: String::from_utf8_lossy(commit.author().name_bytes()).into_owned(),
+                        time: commit.time().seconds(),
+                        file_structure: None,
+                        notes: Vec::new(),
+                        decorations: Vec::new(),
+                    };
+                    if with_file_tree {
+                        let path_filter = match requested_folders {
+                            // If we have been requested to search within certain folders then we do that
+                            Some(requested_folders) => {
+                                PathFilter::for_requested_folders(&requested_folders)?
+                            }
+                            // Else we get the whole file tree
+                            None => PathFilter::everything(),
+                        };
+                        frame.file_structure = get_tree(
+                            commit,
+                            repository,
+                            &path_filter,
+                            with_line_counts,
+                            object_cache,
+                        )
+                    }
+                    Ok(frame)
+                }
+                None => Err("Could not extract commit".to_owned()),
+            },
+            _ => Err("This is not a commit!".to_owned()),
+        },
+        Err(_) => Err("Could not parse the given revision specification".to_owned()),
+    }
+}
+
+/// The tree of the commit of a revision.
+pub fn get_commit_tree<'a>(
+    repository: &'a Repository,
+    commit_id: &str,
+) -> Result<Tree<'a>, String> {
+    match repository.revparse_single(commit_id) {
+        Ok(tree_obj) => match tree_obj.peel_to_commit() {
+            Ok(commit) => match commit.tree() {
+                Ok(tree) => Ok(tree),
+                Err(_) => Err("Could not extract tree of commit".to_owned()),
+            },
+            Err(_) => Err("This is not a commit!".to_owned()),
+        },
+        Err(_) => Err("Could not parse the given revision specification".to_owned()),
+    }
+}
+
+fn get_file_blob(
+    repository: &Repository,
+    entry: &TreeEntry,
+    with_line_counts: bool,
+    object_cache: &mut ObjectCache,
+) -> Option<FileBlob> {
+    match entry.kind {
+        Some(ObjectType::Blob) => Some(FileBlob {
+            object_id: entry.object_id.to_string(),
+            path: entry.root.clone(),
+            name: entry.name.clone(),
+            is_directory: false,
+            size: object_cache.get_blob_size(repository, entry.object_id),
+            file_count: 1,
+            line_count: with_line_counts
+                .then(|| object_cache.get_blob_line_count(repository, entry.object_id)),
+        }),
+        Some(ObjectType::Tree) => {
+            let stats =
+                object_cache.get_directory_stats(repository, entry.object_id, with_line_counts);
+            Some(FileBlob {
+                object_id: entry.object_id.to_string(),
+                path: entry.root.clone(),
+                name: entry.name.clone(),
+                is_directory: true,
+                size: stats.size,
+                file_count: stats.file_count,
+                line_count: stats.line_count,
+            })
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn get_matching_tree_entries(
+    repository: &Repository,
+    tree: &Tree,
+    path_filter: &PathFilter,
+    object_cache: &mut ObjectCache,
+) -> Vec<TreeEntry> {
+    // Get all entries of the tree which match the given filter, in pre-order like `Tree::walk`
+    // Subtrees that can not contain any matching path are never visited, the children of every
+    // tree come from the object cache so an unchanged directory is never read again
+    let mut output: Vec<TreeEntry> = Vec::new();
+    collect_matching_tree_entries(
+        repository,
+        tree.id(),
+        "",
+        path_filter,
+        object_cache,
+        &mut output,
+    );
+    output
+}
+
+fn collect_matching_tree_entries(
+    repository: &Repository,
+    tree_id: Oid,
+    root: &str,
+    path_filter: &PathFilter,
+    object_cache: &mut ObjectCache,
+    output: &mut Vec<TreeEntry>,
+) {
+    for child in object_cache.get_tree_children(repository, tree_id).iter() {
+        let path = root.to_owned() + &child.name;
+        if path_filter.matches(&path) {
+            output.push(TreeEntry {
+                root: root.to_owned(),
+                ..child.clone()
+            });
+        }
+        if child.kind == Some(ObjectType::Tree) {
+            let folder = path + "/";
+            if path_filter.should_descend(&folder) {
+                collect_matching_tree_entries(
+                    repository,
+                    child.object_id,
+                    &folder,
+                    path_filter,
+                    object_cache,
+                    output,
+                );
+            }
+        }
+    }
+}
+
+fn get_tree(
+    commit: &Commit,
+    repository: &Repository,
+    path_filter: &PathFilter,
+    with_line_counts: bool,
+    object_cache: &mut ObjectCache,
+) -> Option<FileTree> {
+    // Get the file tree at the given commit, limited to the paths matching the filter
+    // Line counts need the contents of every file, so they are only counted when asked for
+    match commit.tree() {
+        Ok(tree) => {
+            let mut blobs: Vec<FileBlob> = Vec::new();
+            for entry in
+                get_matching_tree_entries(repository, &tree, path_filter, object_cache).iter()
+            {
+                if let Some(blob) = get_file_blob(repository, entry, with_line_counts, object_cache)
+                {
+                    blobs.push(blob);
+                }
+            }
+            Some(FileTree {
+                object_id: tree.id().to_string(),
+                blobs,
+            })
+        }
+        Err(_) => {
+            println!("Could not extract tree of commit");
+            None
+        }
+    }
+}
+
+/// The contents of a blob, as long as it is UTF-8 text.
+pub fn read_file_contents(repository: &Repository, object_id: &str) -> Result<String, String> {
+    match repository.revparse_single(object_id) {
+        Ok(file_obj) => match file_obj.kind() {
+            Some(ObjectType::Blob) => {
+                let mut contents = String::new();
+                match file_obj
+                    .as_blob()
+                    .unwrap()
+                    .content()
+                    .read_to_string(&mut contents)
+                {
+                    Ok(_) => Ok(contents),
+                    Err(_) => Err("Could not read file".to_owned()),
+                }
+            }
+            _ => Err("This is not a file".to_owned()),
+        },
+        Err(_) => Err("Could not parse the given file object id".to_owned()),
+    }
+}
+
+fn get_commit_parents(commit: &Commit) -> Vec<String> {
+    let mut parents: Vec<String> = Vec::new();
+    for parent_commit in commit.parents() {
+        parents.push(parent_commit.id().to_string());
+    }
+    parents
+}
+
+pub(crate) fn get_file_hashes_for_paths_in_commit(
+    repository: &Repository,
+    git_spec: &str,
+    path_filter: &PathFilter,
+    object_cache: &mut ObjectCache,
+) -> Result<Vec<FileHashByPath>, String> {
+    match repository.revparse_single(git_spec) {
+        Ok(tree_obj) => match tree_obj.kind() {
+            Some(ObjectType::Commit) => match tree_obj.as_commit() {
+                Some(commit) => match commit.tree() {
+                    Ok(tree) => {
+                        let mut output: Vec<FileHashByPath> = Vec::new();
+                        for entry in
+                            get_matching_tree_entries(repository, &tree, path_filter, object_cache)
+                                .iter()
+                        {
+                            if entry.kind == Some(ObjectType::Blob) {
+                                output.push(FileHashByPath {
+                                    path: entry.get_path(),
+                                    hash: entry.object_id.to_string(),
+                                })
+                            }
+                        }
+                        Ok(output)
+                    }
+                    Err(_) => Err("Could not extract tree of commit".to_owned()),
+                },
+                None => Err("Could not extract commit".to_owned()),
+            },
+            _ => Err("This is not a commit!".to_owned()),
+        },
+        Err(_) => Err("Could not parse the given revision specification".to_owned()),
+    }
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::Fixture;
+use gitplay_core::annotations::{AnnotationTarget, ANNOTATIONS_NOTES_REF};
+
+#[test]
+fn annotations_are_visible_at_the_frames_they_cover() {
+    let mut fixture = Fixture::new();
+    let commit_ids: Vec<String> = (0..4)
+        .map(|x| {
+            let contents = format!("{}\n", x);
+            fixture
+                .commit("Change", &[("src/a.txt", Some(contents.as_bytes()))])
+                .to_string()
+        })
+        .collect();
+    let state = fixture.open();
+
+    let on_commit = state
+        .add_annotation(
+            AnnotationTarget::Commit {
+                commit_id: commit_ids[0].clone(),
+            },
+            "The beginning",
+            false,
+        )
+        .unwrap();
+    let on_path = state
+        .add_annotation(
+            AnnotationTarget::Path {
+                commit_id: commit_ids[1].clone(),
+                path: "src/".to_owned(),
+            },
+            "Sources moved here",
+            false,
+        )
+        .unwrap();
+    let on_range = state
+        .add_annotation(
+            AnnotationTarget::Range {
+                from_commit_id: commit_ids[1].clone(),
+                to_commit_id: commit_ids[2].clone(),
+            },
+            "The great refactor",
+            false,
+        )
+        .unwrap();
+    assert!(state
+        .add_annotation(
+            AnnotationTarget::Path {
+                commit_id: commit_ids[1].clone(),
+                path: "docs/".to_owned(),
+            },
+            "Missing",
+            false,
+        )
+        .is_err());
+
+    let visible_ids = |commit_index: usize| -> Vec<String> {
+        state
+            .get_annotations_at_commit(commit_index)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.id)
+            .collect()
+    };
+    assert_eq!(visible_ids(0), vec![on_commit.id.clone()]);
+    let mut at_second = visible_ids(1);
+    at_second.sort();
+    let mut expected = vec![on_path.id.clone(), on_range.id.clone()];
+    expected.sort();
+    assert_eq!(at_second, expected);
+    assert_eq!(visible_ids(2), vec![on_range.id.clone()]);
+    assert!(visible_ids(3).is_empty());
+
+    let updated = state
+        .update_annotation(&on_range.id, "The greatest refactor", false)
+        .unwrap();
+    assert_eq!(updated.text, "The greatest refactor");
+    state.remove_annotation(&on_commit.id).unwrap();
+    assert_eq!(state.list_annotations().unwrap().len(), 2);
+    // Annotations are stored in the Git directory, not the working tree
+    assert!(fixture
+        .path()
+        .join(".git/gitplay/annotations.json")
+        .exists());
+}
+
+#[test]
+fn shared_annotations_travel_as_git_notes() {
+    let mut fixture = Fixture::new();
+    let commit_id = fixture
+        .commit("First", &[("a.txt", Some(b"a\n"))])
+        .to_string();
+    let state = fixture.open();
+
+    let annotation = state
+        .add_annotation(
+            AnnotationTarget::Commit {
+                commit_id: commit_id.clone(),
+            },
+            "Shared with the team",
+            true,
+        )
+        .unwrap();
+    let note = fixture
+        .repository
+        .find_note(
+            Some(ANNOTATIONS_NOTES_REF),
+            git2::Oid::from_str(&commit_id).unwrap(),
+        )
+        .unwrap();
+    assert!(note.message().unwrap().contains("Shared with the team"));
+
+    // Without the local file, like in a clone which fetched the notes
+    std::fs::remove_file(fixture.path().join(".git/gitplay/annotations.json")).unwrap();
+    assert_eq!(state.list_annotations().unwrap(), vec![annotation.clone()]);
+
+    // Not sharing it any more removes the note
+    state
+        .update_annotation(&annotation.id, "Only for me", false)
+        .unwrap();
+    assert!(fixture
+        .repository
+        .find_note(
+            Some(ANNOTATIONS_NOTES_REF),
+            git2::Oid::from_str(&commit_id).unwrap()
+        )
+        .is_err());
+    assert_eq!(state.list_annotations().unwrap()[0].text, "Only for me");
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::Fixture;
+use gitplay_core::buckets::{BucketSize, MAX_EMPTY_BUCKETS};
+
+const DAY: i64 = 86400;
+
+// Commits on Sunday 2020-09-13 (two), Monday 2020-09-14 and Wednesday 2020-10-14, in UTC
+fn fixture_with_uneven_history() -> Fixture {
+    let mut fixture = Fixture::new();
+    fixture.commit("First", &[("a.txt", Some(b"1\n"))]);
+    fixture.commit("Second", &[("a.txt", Some(b"1\n2\n"))]);
+    fixture.wait(DAY);
+    fixture.commit("Third", &[("b.txt", Some(b"b\n"))]);
+    fixture.wait(30 * DAY);
+    fixture.commit("Fourth", &[("a.txt", Some(b"2\n"))]);
+    fixture
+}
+
+#[test]
+fn time_buckets_group_commits_by_calendar_period() {
+    let fixture = fixture_with_uneven_history();
+    let state = fixture.open();
+
+    let days = state.get_time_buckets(BucketSize::Day, false).unwrap();
+    let labels: Vec<&str> = days.iter().map(|x| x.label.as_str()).collect();
+    assert_eq!(labels, vec!["2020-09-13", "2020-09-14", "2020-10-14"]);
+    let commits: Vec<usize> = days.iter().map(|x| x.commits).collect();
+    assert_eq!(commits, vec![2, 1, 1]);
+    assert_eq!(days[0].first_commit_index, Some(0));
+    assert_eq!(days[0].last_commit_index, 1);
+    assert_eq!(days[0].end_time - days[0].start_time, DAY);
+    // Changes of both commits of the first day, by file
+    assert_eq!(days[0].file_changes.len(), 1);
+    assert_eq!(days[0].file_changes[0].path, "a.txt");
+    assert_eq!(days[0].file_changes[0].commits, 2);
+    assert_eq!((days[0].additions, days[0].deletions), (2, 0));
+
+    // Weeks start on Monday
+    let weeks = state.get_time_buckets(BucketSize::Week, false).unwrap();
+    let labels: Vec<&str> = weeks.iter().map(|x| x.label.as_str()).collect();
+    assert_eq!(labels, vec!["2020-09-07", "2020-09-14", "2020-10-12"]);
+
+    let months = state.get_time_buckets(BucketSize::Month, false).unwrap();
+    let labels: Vec<&str> = months.iter().map(|x| x.label.as_str()).collect();
+    assert_eq!(labels, vec!["2020-09", "2020-10"]);
+    assert_eq!(months[0].end_time, months[1].start_time);
+    assert_eq!(months[0].end_time - months[0].start_time, 30 * DAY);
+    assert_eq!(months[0].last_commit_index, 2);
+
+    let hours = state.get_time_buckets(BucketSize::Hour, false).unwrap();
+    assert_eq!(hours[0].label, "2020-09-13 12:00");
+}
+
+#[test]
+fn empty_time_buckets_show_the_last_frame_before_them() {
+    let fixture = fixture_with_uneven_history();
+    let state = fixture.open();
+
+    let weeks = state.get_time_buckets(BucketSize::Week, true).unwrap();
+    let labels: Vec<&str> = weeks.iter().map(|x| x.label.as_str()).collect();
+    assert_eq!(
+        labels,
+        vec![
+            "2020-09-07",
+            "2020-09-14",
+            "2020-09-21",
+            "2020-09-28",
+            "2020-10-05",
+            "2020-10-12"
+        ]
+    );
+    for week in &weeks[2..5] {
+        assert_eq!(week.commits, 0);
+        assert_eq!(week.first_commit_index, None);
+        assert_eq!(week.last_commit_index, 2);
+        assert_eq!(week.last_commit_id, weeks[1].last_commit_id);
+        assert!(week.file_changes.is_empty());
+    }
+    assert_eq!(weeks[5].last_commit_index, 3);
+}
+
+#[test]
+fn find_commit_at_time_returns_the_frame_current_then() {
+    let fixture = fixture_with_uneven_history();
+    let state = fixture.open();
+    let summaries = state.get_commit_summaries(None, None).unwrap();
+
+    assert_eq!(
+        state.find_commit_at_time(summaries[0].time - 1).unwrap(),
+        None
+    );
+    assert_eq!(
+        state.find_commit_at_time(summaries[0].time).unwrap(),
+        Some(0)
+    );
+    assert_eq!(
+        state.find_commit_at_time(summaries[1].time + 1).unwrap(),
+        Some(1)
+    );
+    // 2020-10-01, between the third and the fourth commit
+    assert_eq!(state.find_commit_at_time(1_601_510_400).unwrap(), Some(2));
+    assert_eq!(state.find_commit_at_time(i64::MAX).unwrap(), Some(3));
+}
+
+#[test]
+fn empty_time_buckets_are_limited() {
+    let mut fixture = Fixture::new();
+    fixture.commit("First", &[("a.txt", Some(b"1\n"))]);
+    fixture.wait(2 * 365 * DAY);
+    fixture.commit("Second", &[("a.txt", Some(b"2\n"))]);
+    let state = fixture.open();
+
+    // Two years of empty hours are too many, two years of empty days are not
+    let err = state.get_time_buckets(BucketSize::Hour, true).unwrap_err();
+    assert!(err.contains(&MAX_EMPTY_BUCKETS.to_string()));
+    assert_eq!(
+        state
+            .get_time_buckets(BucketSize::Hour, false)
+            .unwrap()
+            .len(),
+        2
+    );
+    assert_eq!(
+        state.get_time_buckets(BucketSize::Day, true).unwrap().len(),
+        2 * 365 + 1
+    );
+}
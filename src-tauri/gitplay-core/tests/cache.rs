@@ -0,0 +1,225 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::Fixture;
+use gitplay_core::{buckets::BucketSize, cache::GitplayState};
+
+#[test]
+fn operations_need_an_open_repository() {
+    let state = GitplayState::new();
+    assert!(state.prepare_cache().is_err());
+    assert!(state.get_commit_details("HEAD", vec![""], false).is_err());
+    assert!(state.get_sizes_for_paths(vec![""], None, None).is_err());
+    assert!(state.get_files_ordered_by_most_modifications(None).is_err());
+    assert!(state.read_file_contents("HEAD:a.txt").is_err());
+}
+
+#[test]
+fn prepare_cache_of_empty_repository() {
+    let fixture = Fixture::new();
+    let state = GitplayState::new();
+    state.open(fixture.path().to_path_buf()).unwrap();
+    assert_eq!(state.prepare_cache().unwrap(), (0, vec![]));
+    assert!(state.get_commits(None, None).unwrap().is_empty());
+}
+
+#[test]
+fn get_commit_details_and_read_file_contents() {
+    let mut fixture = Fixture::new();
+    fixture.commit("Add file", &[("src/a.txt", Some(b"hello\n"))]);
+    let state = fixture.open();
+
+    let frame = state
+        .get_commit_details("HEAD", vec!["src/"], false)
+        .unwrap();
+    let blobs = frame.get_file_structure().unwrap().get_blobs();
+    assert_eq!(blobs.len(), 1);
+    assert_eq!(blobs[0].get_name(), "a.txt");
+    assert_eq!(
+        state.read_file_contents(blobs[0].get_object_id()).unwrap(),
+        "hello\n"
+    );
+    // Lines are only counted when asked for
+    assert_eq!(blobs[0].get_line_count(), None);
+    let frame = state.get_commit_details("HEAD", vec![""], true).unwrap();
+    let blobs = frame.get_file_structure().unwrap().get_blobs();
+    assert_eq!(blobs[0].get_name(), "src");
+    assert_eq!(blobs[0].get_line_count(), Some(1));
+}
+
+#[test]
+fn get_sizes_for_paths_marks_commits_changing_each_file() {
+    let mut fixture = Fixture::new();
+    let first = fixture.commit(
+        "Add files",
+        &[("a.txt", Some(b"1\n")), ("b.txt", Some(b"1\n"))],
+    );
+    let second = fixture.commit("Change a", &[("a.txt", Some(b"2\n"))]);
+    let third = fixture.commit("Change b", &[("b.txt", Some(b"2\n"))]);
+    let fourth = fixture.rename("Rename a", "a.txt", "c.txt");
+    fixture.commit("Add nested", &[("src/d.txt", Some(b"1\n"))]);
+    let state = fixture.open();
+
+    let sizes = state.get_sizes_for_paths(vec![""], None, None).unwrap();
+    let sorted = |commit_ids: &[git2::Oid]| -> Vec<String> {
+        let mut commit_ids: Vec<String> = commit_ids.iter().map(|x| x.to_string()).collect();
+        commit_ids.sort();
+        commit_ids
+    };
+    assert_eq!(changed_in(&sizes, "a.txt"), sorted(&[first, second]));
+    assert_eq!(changed_in(&sizes, "b.txt"), sorted(&[first, third]));
+    assert_eq!(changed_in(&sizes, "c.txt"), sorted(&[fourth]));
+    // Only the files directly within the requested folders are listed
+    assert!(!sizes.contains_key("src/d.txt"));
+
+    let sizes = state.get_sizes_for_paths(vec!["src/"], None, None).unwrap();
+    assert_eq!(sizes.keys().collect::<Vec<_>>(), vec!["src/d.txt"]);
+
+    // A range of the timeline only sees the changes within it
+    let sizes = state
+        .get_sizes_for_paths(vec![""], Some(1), Some(2))
+        .unwrap();
+    assert_eq!(changed_in(&sizes, "a.txt"), vec![second.to_string()]);
+    assert_eq!(changed_in(&sizes, "b.txt"), sorted(&[second, third]));
+}
+
+fn changed_in(sizes: &HashMap<String, HashMap<String, bool>>, path: &str) -> Vec<String> {
+    let mut commit_ids: Vec<String> = sizes[path].keys().cloned().collect();
+    commit_ids.sort();
+    commit_ids
+}
+
+#[test]
+fn get_files_ordered_by_most_modifications_counts_sampled_changes() {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "Add files",
+        &[
+            ("hot.txt", Some(b"0\n")),
+            ("warm.txt", Some(b"0\n")),
+            ("cold.txt", Some(b"0\n")),
+        ],
+    );
+    for index in 1..9 {
+        let contents = format!("{}\n", index);
+        let mut changes = vec![("hot.txt", Some(contents.as_bytes()))];
+        if index == 5 {
+            changes.push(("warm.txt", Some(contents.as_bytes())));
+        }
+        fixture.commit(&format!("Change {}", index), &changes);
+    }
+    let state = fixture.open();
+
+    // Every fourth commit is compared, files changed in only one sample are left out
+    assert_eq!(
+        state.get_files_ordered_by_most_modifications(None).unwrap(),
+        vec![("hot.txt".to_owned(), 3), ("warm.txt".to_owned(), 2)]
+    );
+    let mut from_fifth = state
+        .get_files_ordered_by_most_modifications(Some(4))
+        .unwrap();
+    from_fifth.sort();
+    assert_eq!(
+        from_fifth,
+        vec![("hot.txt".to_owned(), 2), ("warm.txt".to_owned(), 2)]
+    );
+}
+
+#[test]
+fn commit_summaries_are_decorated_with_refs() {
+    let mut fixture = Fixture::new();
+    let first_id = fixture.commit("First", &[("a.txt", Some(b"1\n"))]);
+    fixture.tag("v1.0", Some("Release 1.0"));
+    fixture
+        .repository
+        .reference("refs/remotes/origin/main", first_id, false, "Fetched")
+        .unwrap();
+    fixture.commit("Second", &[("a.txt", Some(b"2\n"))]);
+    fixture.branch("feature");
+    let state = fixture.open();
+
+    let names = |commit_index: usize| -> Vec<String> {
+        state.get_commit_summaries(None, None).unwrap()[commit_index]
+            .decorations
+            .iter()
+            .map(|x| x.name.clone())
+            .collect()
+    };
+    assert_eq!(names(0), vec!["origin/main", "v1.0"]);
+    // The branch HEAD points at comes first
+    assert_eq!(names(1), vec!["main", "feature"]);
+    let second = &state.get_commit_summaries(Some(1), Some(1)).unwrap()[0];
+    assert!(second.decorations[0].is_head);
+    assert!(!second.decorations[1].is_head);
+
+    // Decorations are updated when the cache is prepared again
+    fixture.detach_head(first_id);
+    state.prepare_cache().unwrap();
+    assert_eq!(names(0), vec!["HEAD", "origin/main", "v1.0"]);
+    let frame = state
+        .get_commit_details(&first_id.to_string(), vec![], false)
+        .unwrap();
+    assert_eq!(frame.get_decorations().len(), 3);
+}
+
+#[test]
+fn commit_details_after_reopening_before_prepare_cache() {
+    let mut fixture = Fixture::new();
+    let commit_id = fixture
+        .commit("Add file", &[("a.txt", Some(b"a\n"))])
+        .to_string();
+    let state = fixture.open();
+
+    // The cached timeline of the previous session is gone, frames are still loaded
+    state.open(fixture.path().to_path_buf()).unwrap();
+    let frame = state.get_commit_details(&commit_id, vec![], false).unwrap();
+    assert!(frame.get_decorations().is_empty());
+    state.prepare_cache().unwrap();
+    let frame = state.get_commit_details(&commit_id, vec![], false).unwrap();
+    assert_eq!(frame.get_decorations()[0].name, "main");
+}
+
+#[test]
+fn timeline_operations_need_a_prepared_cache_after_reopening() {
+    let mut fixture = Fixture::new();
+    fixture.commit("Add file", &[("a.txt", Some(b"a\n"))]);
+    fixture.commit("Change file", &[("a.txt", Some(b"b\n"))]);
+    let state = fixture.open();
+    assert_eq!(state.get_commit_summaries(None, None).unwrap().len(), 2);
+
+    state.open(fixture.path().to_path_buf()).unwrap();
+    assert_eq!(
+        state.get_commit_summaries(None, None).unwrap_err(),
+        "Cache not prepared"
+    );
+    assert_eq!(
+        state.get_file_history("a.txt").unwrap_err(),
+        "Cache not prepared"
+    );
+    assert!(state.get_commits(None, None).is_err());
+    assert!(state.get_file_churn(None, None).is_err());
+    assert_eq!(
+        state.find_commit_at_time(i64::MAX).unwrap_err(),
+        "Cache not prepared"
+    );
+    assert!(state.get_commit_graph(None, None).is_err());
+    assert!(state.get_tags().is_err());
+    assert!(state
+        .find_commits_changing_string("b", false, vec![])
+        .is_err());
+    assert!(state.get_time_buckets(BucketSize::Day, false).is_err());
+
+    // Nothing was left locked by the failed calls
+    state.prepare_cache().unwrap();
+    assert_eq!(state.get_commit_summaries(None, None).unwrap().len(), 2);
+    assert_eq!(state.get_file_history("a.txt").unwrap().len(), 2);
+    assert_eq!(state.find_commit_at_time(i64::MAX).unwrap(), Some(1));
+    assert_eq!(state.get_commit_graph(None, None).unwrap().len(), 2);
+    let matches = state
+        .find_commits_changing_string("b", false, vec![])
+        .unwrap();
+    assert_eq!(matches[0].commit_index, 1);
+    // Ranges past the end of the timeline are empty
+    assert!(state.get_commits(Some(5), None).unwrap().is_empty());
+}
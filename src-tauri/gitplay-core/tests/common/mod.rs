@@ -0,0 +1,228 @@
+// Not every test file uses every part of the fixture builder
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use git2::{Index, IndexEntry, IndexTime, ObjectType, Oid, Repository, Signature, Time};
+use gitplay_core::cache::GitplayState;
+use tempfile::TempDir;
+
+/*
+Fixture is a Git repository in a temporary folder, built commit by commit for a test. Commits are
+written straight to the object database, so messages do not have to be UTF-8, and each commit is
+one minute after the previous one, so the timeline order is always the order they were made in.
+The folder is removed when the fixture is dropped.
+ */
+pub struct Fixture {
+    folder: TempDir,
+    pub repository: Repository,
+    time: i64,
+}
+
+// A change to the tree of a commit: the contents of a file, or None to delete it
+pub type Change<'a> = (&'a str, Option<&'a [u8]>);
+
+impl Fixture {
+    // An empty repository, with HEAD pointing to the unborn branch main
+    pub fn new() -> Self {
+        let folder = TempDir::new().unwrap();
+        let repository = Repository::init(folder.path()).unwrap();
+        repository.set_head("refs/heads/main").unwrap();
+        Fixture {
+            folder,
+            repository,
+            time: 1_600_000_000,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.folder.path()
+    }
+
+    // Commit the changes on top of HEAD, moving the current branch (or a detached HEAD)
+    pub fn commit(&mut self, message: &str, changes: &[Change]) -> Oid {
+        self.commit_with_message_bytes(message.as_bytes(), changes)
+    }
+
+    pub fn commit_with_message_bytes(&mut self, message: &[u8], changes: &[Change]) -> Oid {
+        let parents: Vec<Oid> = self.head_commit().into_iter().collect();
+        self.write_commit(message, &parents, changes)
+    }
+
+    // Point the submodule at the given path to a commit of another repository, by writing a
+    // gitlink to the tree. The submodule repository itself is never needed
+    pub fn update_submodule(&mut self, message: &str, path: &str, commit_id: Oid) -> Oid {
+        let parents: Vec<Oid> = self.head_commit().into_iter().collect();
+        let mut index = self.index_of(&parents);
+        let mut entry = index_entry(path, commit_id, 0);
+        entry.mode = 0o160000;
+        index.add(&entry).unwrap();
+        self.write_index(message.as_bytes(), &parents, &mut index)
+    }
+
+    // Move the clock forward, the next commit is made the given number of seconds later
+    pub fn wait(&mut self, seconds: i64) {
+        self.time += seconds;
+    }
+
+    // Create a branch at HEAD, without switching to it
+    pub fn branch(&self, name: &str) {
+        let head = self
+            .repository
+            .find_commit(self.head_commit().unwrap())
+            .unwrap();
+        self.repository.branch(name, &head, false).unwrap();
+    }
+
+    pub fn checkout(&self, branch_name: &str) {
+        self.repository
+            .set_head(&format!("refs/heads/{}", branch_name))
+            .unwrap();
+    }
+
+    // Merge the branch into HEAD. The tree of the merge is the tree of HEAD with the changes,
+    // so conflicts are resolved by the test
+    pub fn merge(&mut self, message: &str, branch_name: &str, changes: &[Change]) -> Oid {
+        let branch_commit = self
+            .repository
+            .revparse_single(&format!("refs/heads/{}", branch_name))
+            .unwrap()
+            .id();
+        let parents = vec![self.head_commit().unwrap(), branch_commit];
+        self.write_commit(message.as_bytes(), &parents, changes)
+    }
+
+    // Move a file without changing its contents
+    pub fn rename(&mut self, message: &str, from: &str, to: &str) -> Oid {
+        let contents = self.read_at_head(from);
+        self.commit(message, &[(from, None), (to, Some(&contents))])
+    }
+
+    // Tag HEAD, with an annotated tag if there is a message
+    pub fn tag(&self, name: &str, message: Option<&str>) {
+        let head = self.repository.head().unwrap().peel_to_commit().unwrap();
+        match message {
+            Some(message) => {
+                let tagger =
+                    Signature::new("Fixture", "fixture@example.com", &Time::new(self.time, 0))
+                        .unwrap();
+                self.repository
+                    .tag(name, head.as_object(), &tagger, message, false)
+                    .unwrap();
+            }
+            None => {
+                self.repository
+                    .tag_lightweight(name, head.as_object(), false)
+                    .unwrap();
+            }
+        }
+    }
+
+    pub fn detach_head(&self, commit_id: Oid) {
+        self.repository.set_head_detached(commit_id).unwrap();
+    }
+
+    pub fn read_at_head(&self, path: &str) -> Vec<u8> {
+        let head = self
+            .repository
+            .find_commit(self.head_commit().unwrap())
+            .unwrap();
+        let entry = head.tree().unwrap().get_path(Path::new(path)).unwrap();
+        let blob = self.repository.find_blob(entry.id()).unwrap();
+        blob.content().to_vec()
+    }
+
+    // A session on this repository, with the timeline loaded like the desktop application does
+    pub fn open(&self) -> GitplayState {
+        let state = GitplayState::new();
+        state.open(self.path().to_path_buf()).unwrap();
+        state.prepare_cache().unwrap();
+        state
+    }
+
+    fn head_commit(&self) -> Option<Oid> {
+        self.repository
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok())
+            .map(|commit| commit.id())
+    }
+
+    fn index_of(&self, parents: &[Oid]) -> Index {
+        // The tree of the first parent, in a standalone index
+        let mut index = Index::new().unwrap();
+        if let Some(parent) = parents.first() {
+            let parent = self.repository.find_commit(*parent).unwrap();
+            index.read_tree(&parent.tree().unwrap()).unwrap();
+        }
+        index
+    }
+
+    fn write_commit(&mut self, message: &[u8], parents: &[Oid], changes: &[Change]) -> Oid {
+        let mut index = self.index_of(parents);
+        for (path, contents) in changes {
+            match contents {
+                Some(contents) => {
+                    let blob_id = self.repository.blob(contents).unwrap();
+                    index
+                        .add(&index_entry(path, blob_id, contents.len()))
+                        .unwrap();
+                }
+                None => index.remove_path(Path::new(path)).unwrap(),
+            }
+        }
+        self.write_index(message, parents, &mut index)
+    }
+
+    fn write_index(&mut self, message: &[u8], parents: &[Oid], index: &mut Index) -> Oid {
+        let tree_id = index.write_tree_to(&self.repository).unwrap();
+
+        self.time += 60;
+        let signature = format!("Fixture <fixture@example.com> {} +0000", self.time);
+        let mut buffer = format!("tree {}\n", tree_id).into_bytes();
+        for parent in parents {
+            buffer.extend(format!("parent {}\n", parent).bytes());
+        }
+        buffer.extend(format!("author {}\ncommitter {}\n", signature, signature).bytes());
+        if std::str::from_utf8(message).is_err() {
+            buffer.extend(b"encoding ISO-8859-1\n");
+        }
+        buffer.push(b'\n');
+        buffer.extend(message);
+        let commit_id = self
+            .repository
+            .odb()
+            .unwrap()
+            .write(ObjectType::Commit, &buffer)
+            .unwrap();
+
+        // Move the branch HEAD points to, which creates it for the first commit
+        let head = self.repository.find_reference("HEAD").unwrap();
+        match head.symbolic_target() {
+            Some(branch) => {
+                self.repository
+                    .reference(branch, commit_id, true, "fixture commit")
+                    .unwrap();
+            }
+            None => self.repository.set_head_detached(commit_id).unwrap(),
+        }
+        commit_id
+    }
+}
+
+fn index_entry(path: &str, blob_id: Oid, size: usize) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: 0o100644,
+        uid: 0,
+        gid: 0,
+        file_size: size as u32,
+        id: blob_id,
+        flags: path.len().min(0xfff) as u16,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use common::Fixture;
+use gitplay_core::diff::{CommitDiff, MergeDiffMode};
+
+fn paths(diff: &CommitDiff) -> Vec<Vec<String>> {
+    diff.parents
+        .iter()
+        .map(|x| x.files.iter().map(|y| y.path.clone()).collect())
+        .collect()
+}
+
+// main and feature change different files from a common base, then feature is merged into main
+fn fixture_with_branches() -> Fixture {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "Base",
+        &[
+            ("a.txt", Some(b"a\n")),
+            ("b.txt", Some(b"b\n")),
+            ("c.txt", Some(b"c\n")),
+        ],
+    );
+    fixture.branch("feature");
+    fixture.commit("On main", &[("a.txt", Some(b"a\nmain\n"))]);
+    fixture.checkout("feature");
+    fixture.commit("On feature", &[("b.txt", Some(b"b\nfeature\n"))]);
+    fixture.checkout("main");
+    fixture
+}
+
+#[test]
+fn merge_diff_modes() {
+    let mut fixture = fixture_with_branches();
+    let merge_id = fixture
+        .merge(
+            "Merge feature",
+            "feature",
+            &[("b.txt", Some(b"b\nfeature\n"))],
+        )
+        .to_string();
+    let state = fixture.open();
+
+    let first_parent = state
+        .get_commit_diff(&merge_id, MergeDiffMode::FirstParent)
+        .unwrap();
+    assert!(first_parent.is_merge);
+    assert_eq!(paths(&first_parent), vec![vec!["b.txt"]]);
+    assert_eq!(first_parent.parents[0].files[0].additions, 1);
+
+    let per_parent = state
+        .get_commit_diff(&merge_id, MergeDiffMode::PerParent)
+        .unwrap();
+    assert_eq!(paths(&per_parent), vec![vec!["b.txt"], vec!["a.txt"]]);
+
+    // A clean merge decided nothing
+    let combined = state
+        .get_commit_diff(&merge_id, MergeDiffMode::Combined)
+        .unwrap();
+    assert_eq!(paths(&combined), vec![Vec::<String>::new()]);
+    assert!(combined.evil_changes.is_empty());
+
+    // Commits which are not merges have one diff in every mode
+    let head_of_main = state.get_commit_summaries(Some(1), Some(1)).unwrap()[0]
+        .commit_id
+        .clone();
+    let diff = state
+        .get_commit_diff(&head_of_main, MergeDiffMode::PerParent)
+        .unwrap();
+    assert!(!diff.is_merge);
+    assert_eq!(paths(&diff), vec![vec!["a.txt"]]);
+}
+
+#[test]
+fn evil_merges_are_detected() {
+    let mut fixture = fixture_with_branches();
+    // c.txt was changed by neither branch
+    let merge_id = fixture
+        .merge(
+            "Merge feature",
+            "feature",
+            &[
+                ("b.txt", Some(b"b\nfeature\n")),
+                ("c.txt", Some(b"c\nsneaky\n")),
+            ],
+        )
+        .to_string();
+    let state = fixture.open();
+
+    let combined = state
+        .get_commit_diff(&merge_id, MergeDiffMode::Combined)
+        .unwrap();
+    assert_eq!(paths(&combined), vec![vec!["c.txt"]]);
+    assert_eq!(combined.evil_changes.len(), 1);
+    let evil = &combined.evil_changes[0];
+    assert_eq!(evil.path, "c.txt");
+    assert!(!evil.conflicted);
+    assert_eq!((evil.additions, evil.deletions), (1, 0));
+}
+
+#[test]
+fn conflicts_resolved_with_new_lines_are_evil() {
+    let mut fixture = Fixture::new();
+    fixture.commit("Base", &[("a.txt", Some(b"version = 1\n"))]);
+    fixture.branch("feature");
+    fixture.commit("On main", &[("a.txt", Some(b"version = 2\n"))]);
+    fixture.checkout("feature");
+    fixture.commit("On feature", &[("a.txt", Some(b"version = 3\n"))]);
+    fixture.checkout("main");
+    let picked_side = fixture
+        .merge(
+            "Merge feature",
+            "feature",
+            &[("a.txt", Some(b"version = 3\n"))],
+        )
+        .to_string();
+    fixture.checkout("feature");
+    fixture.commit("Again on feature", &[("a.txt", Some(b"version = 5\n"))]);
+    fixture.checkout("main");
+    fixture.commit("Again on main", &[("a.txt", Some(b"version = 6\n"))]);
+    let new_line = fixture
+        .merge(
+            "Merge feature again",
+            "feature",
+            &[("a.txt", Some(b"version = 4\n"))],
+        )
+        .to_string();
+    let state = fixture.open();
+
+    let diff = state
+        .get_commit_diff(&picked_side, MergeDiffMode::Combined)
+        .unwrap();
+    assert!(diff.evil_changes.is_empty());
+
+    let diff = state
+        .get_commit_diff(&new_line, MergeDiffMode::Combined)
+        .unwrap();
+    assert_eq!(diff.evil_changes.len(), 1);
+    assert!(diff.evil_changes[0].conflicted);
+    assert_eq!(diff.evil_changes[0].additions, 1);
+}
+
+#[test]
+fn merges_touching_submodules() {
+    let mut fixture = Fixture::new();
+    let library_commit = |x: &str| git2::Oid::from_str(x).unwrap();
+    fixture.commit("Base", &[("a.txt", Some(b"a\n"))]);
+    fixture.update_submodule(
+        "Add library",
+        "lib",
+        library_commit("1111111111111111111111111111111111111111"),
+    );
+    fixture.branch("feature");
+    fixture.update_submodule(
+        "Update library",
+        "lib",
+        library_commit("2222222222222222222222222222222222222222"),
+    );
+    fixture.checkout("feature");
+    fixture.commit("On feature", &[("b.txt", Some(b"b\n"))]);
+    fixture.checkout("main");
+    let merge_id = fixture
+        .merge("Merge feature", "feature", &[("b.txt", Some(b"b\n"))])
+        .to_string();
+    let state = fixture.open();
+
+    // The submodule is a commit of another repository, not a file with lines
+    let per_parent = state
+        .get_commit_diff(&merge_id, MergeDiffMode::PerParent)
+        .unwrap();
+    assert_eq!(paths(&per_parent), vec![vec!["b.txt"], vec!["lib"]]);
+    let combined = state
+        .get_commit_diff(&merge_id, MergeDiffMode::Combined)
+        .unwrap();
+    assert!(combined.evil_changes.is_empty());
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn get_gource_log_lists_changes_in_timeline_order() {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "Add files",
+        &[
+            ("README.md", Some(b"# Fixture\n")),
+            ("src/a.rs", Some(b"a\n")),
+        ],
+    );
+    fixture.commit("Change a", &[("src/a.rs", Some(b"b\n"))]);
+    fixture.rename("Move README", "README.md", "docs/README.md");
+    let state = fixture.open();
+
+    let commits = state.get_commit_summaries(None, None).unwrap();
+    let time = |index: usize| commits[index].time;
+    assert_eq!(
+        state.get_gource_log(None, None).unwrap(),
+        format!(
+            "{0}|Fixture|A|/README.md\n\
+             {0}|Fixture|A|/src/a.rs\n\
+             {1}|Fixture|M|/src/a.rs\n\
+             {2}|Fixture|D|/README.md\n\
+             {2}|Fixture|A|/docs/README.md\n",
+            time(0),
+            time(1),
+            time(2)
+        )
+    );
+    assert_eq!(
+        state.get_gource_log(Some(1), Some(1)).unwrap(),
+        format!("{}|Fixture|M|/src/a.rs\n", time(1))
+    );
+}
+
+#[test]
+fn export_gource_log_writes_file() {
+    let mut fixture = Fixture::new();
+    fixture.commit("First", &[("a.txt", Some(b"1\n")), ("b.txt", Some(b"1\n"))]);
+    fixture.commit("Second", &[("b.txt", None)]);
+    let state = fixture.open();
+
+    let file_path = fixture.path().join("gource.log");
+    let file_path = file_path.to_str().unwrap();
+    assert_eq!(state.export_gource_log(file_path, None, None).unwrap(), 3);
+    assert_eq!(
+        std::fs::read_to_string(file_path).unwrap(),
+        state.get_gource_log(None, None).unwrap()
+    );
+}
+
+#[test]
+fn get_report_tables() {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "First",
+        &[("a.txt", Some(b"1\n")), ("b.txt", Some(b"1\n2\n"))],
+    );
+    fixture.commit("Second", &[("a.txt", Some(b"1\nchanged\n"))]);
+    fixture.commit("Third", &[("b.txt", None)]);
+    let state = fixture.open();
+
+    let report = state.get_report(None, None).unwrap();
+    assert_eq!((report.start_index, report.end_index), (0, 3));
+    assert_eq!(report.commits.len(), 3);
+    assert_eq!(report.commits[1].commit_message, "Second");
+    assert_eq!(report.commits[1].author, "Fixture");
+
+    let file_changes: Vec<(&str, usize, usize, usize)> = report
+        .file_changes
+        .iter()
+        .map(|x| (x.path.as_str(), x.commits, x.additions, x.deletions))
+        .collect();
+    assert_eq!(file_changes, vec![("a.txt", 2, 2, 0), ("b.txt", 2, 2, 2)]);
+
+    assert_eq!(report.authors.len(), 1);
+    let author = &report.authors[0];
+    assert_eq!((author.commits, author.files_changed), (3, 4));
+    assert_eq!((author.additions, author.deletions), (4, 2));
+    assert_eq!(author.first_commit_time, report.commits[0].time);
+    assert_eq!(author.last_commit_time, report.commits[2].time);
+
+    let frame_sizes: Vec<(usize, usize)> = report
+        .frame_sizes
+        .iter()
+        .map(|x| (x.size, x.file_count))
+        .collect();
+    assert_eq!(frame_sizes, vec![(6, 2), (14, 2), (10, 1)]);
+
+    let report = state.get_report(Some(2), Some(5)).unwrap();
+    assert_eq!((report.start_index, report.end_index), (2, 3));
+    assert_eq!(report.frame_sizes[0].commit_index, 2);
+}
+
+#[test]
+fn export_report_writes_csv_and_json() {
+    let mut fixture = Fixture::new();
+    fixture.commit("First, with a comma", &[("a.txt", Some(b"1\n"))]);
+    let state = fixture.open();
+
+    let folder = fixture.path().join("report");
+    let paths = state
+        .export_report(folder.to_str().unwrap(), None, None)
+        .unwrap();
+    assert_eq!(paths.len(), 5);
+    let commits = std::fs::read_to_string(folder.join("commits.csv")).unwrap();
+    let mut lines = commits.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "commit_index,commit_id,commit_message,author,time,decorations"
+    );
+    let row = lines.next().unwrap();
+    assert!(row.contains(",\"First, with a comma\",Fixture,"));
+    assert!(row.ends_with(",HEAD -> main"));
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(folder.join("report.json")).unwrap())
+            .unwrap();
+    assert_eq!(json["authors"][0]["author"], "Fixture");
+}
+
+#[test]
+fn exports_need_a_prepared_cache() {
+    let mut fixture = Fixture::new();
+    fixture.commit("First", &[("a.txt", Some(b"1\n"))]);
+    let state = gitplay_core::GitplayState::new();
+    state.open(fixture.path().to_path_buf()).unwrap();
+
+    assert_eq!(
+        state.get_gource_log(None, None).unwrap_err(),
+        "Cache not prepared"
+    );
+    let log_path = fixture.path().join("gitplay.log");
+    assert!(state
+        .export_gource_log(log_path.to_str().unwrap(), None, None)
+        .is_err());
+
+    assert_eq!(
+        state.get_report(None, None).unwrap_err(),
+        "Cache not prepared"
+    );
+    let folder = fixture.path().join("report");
+    assert!(state
+        .export_report(folder.to_str().unwrap(), None, None)
+        .is_err());
+    assert!(state.get_html_report(None, None).is_err());
+    let html_path = fixture.path().join("report.html");
+    assert!(state
+        .export_html_report(html_path.to_str().unwrap(), None, None)
+        .is_err());
+    assert!(!folder.exists() && !html_path.exists());
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::Fixture;
+use gitplay_core::graph::{GraphEdge, GraphRow};
+
+fn edges(row: &GraphRow) -> Vec<(usize, usize)> {
+    row.edges
+        .iter()
+        .map(
+            |GraphEdge {
+                 from_column,
+                 to_column,
+             }| (*from_column, *to_column),
+        )
+        .collect()
+}
+
+#[test]
+fn graph_layout_of_a_merged_branch() {
+    let mut fixture = Fixture::new();
+    fixture.commit("Root", &[("a.txt", Some(b"a\n"))]);
+    fixture.branch("feature");
+    fixture.commit("On main", &[("a.txt", Some(b"main\n"))]);
+    fixture.checkout("feature");
+    fixture.commit("On feature", &[("b.txt", Some(b"b\n"))]);
+    fixture.checkout("main");
+    fixture.merge("Merge feature", "feature", &[("b.txt", Some(b"b\n"))]);
+    let state = fixture.open();
+
+    // Oldest first: Root, On main, On feature, Merge feature
+    let graph = state.get_commit_graph(None, None).unwrap();
+    assert_eq!(graph.len(), 4);
+    let columns: Vec<usize> = graph.iter().map(|x| x.column).collect();
+    assert_eq!(columns, vec![0, 0, 1, 0]);
+
+    assert!(graph[3].is_merge);
+    assert_eq!(edges(&graph[3]), vec![(0, 0), (0, 1)]);
+    assert_eq!(edges(&graph[2]), vec![(0, 0), (1, 1)]);
+    // The line of the feature branch joins the first column at the fork
+    assert_eq!(edges(&graph[1]), vec![(0, 0), (1, 0)]);
+    assert!(graph[0].is_fork);
+    assert!(edges(&graph[0]).is_empty());
+    assert_eq!(graph[3].width, 2);
+    assert_eq!(graph[0].width, 1);
+
+    // Paged like the commits
+    let page = state.get_commit_graph(Some(2), Some(10)).unwrap();
+    assert_eq!(page.len(), 2);
+    assert_eq!(page[0].commit_index, 2);
+}
+
+#[test]
+fn graph_layout_of_a_linear_history() {
+    let mut fixture = Fixture::new();
+    for x in 0..3 {
+        let contents = format!("{}\n", x);
+        fixture.commit("Change", &[("a.txt", Some(contents.as_bytes()))]);
+    }
+    let state = fixture.open();
+
+    let graph = state.get_commit_graph(None, None).unwrap();
+    assert!(graph
+        .iter()
+        .all(|x| x.column == 0 && !x.is_merge && !x.is_fork));
+    assert_eq!(edges(&graph[2]), vec![(0, 0)]);
+    assert!(edges(&graph[0]).is_empty());
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn get_html_report_has_all_sections() {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "First",
+        &[
+            ("src/main.rs", Some(b"fn main() {}\n")),
+            ("docs/guide.md", Some(b"# Guide\n")),
+        ],
+    );
+    fixture.tag("v0.1.0", None);
+    fixture.commit("Second", &[("src/main.rs", Some(b"fn main() { run() }\n"))]);
+    fixture.tag("v0.2.0", Some("Release <0.2.0>"));
+    let state = fixture.open();
+
+    let html = state.get_html_report(None, None).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    for section in [
+        "Overview",
+        "Hotspots",
+        "Top contributors",
+        "Major directories",
+        "Releases",
+    ] {
+        assert!(
+            html.contains(&format!("<h2>{}</h2>", section)),
+            "{}",
+            section
+        );
+    }
+    // Both ends of the range are frames of the report
+    assert!(html.contains("<dt>Frames</dt><dd>0 to 1</dd>"));
+    let second = state.get_html_report(Some(1), Some(1)).unwrap();
+    assert!(second.contains("<dt>Frames</dt><dd>1 to 1</dd>"));
+    assert!(html.contains("<td>src/main.rs</td>"));
+    assert!(html.contains("<td>Fixture</td>"));
+    // Both directories are drawn in the chart
+    assert_eq!(html.matches("<polyline").count(), 2);
+    assert!(html.contains("src/ ("));
+    // Fixture commits start at 2020-09-13
+    assert!(html.contains("<td>v0.1.0</td><td>2020-09-13</td><td>0</td>"));
+    assert!(html.contains("<td>v0.2.0</td><td>2020-09-13</td><td>1</td>"));
+    // Nothing is loaded from elsewhere
+    assert!(!html.contains("http"));
+}
+
+#[test]
+fn get_html_report_of_repository_without_tags() {
+    let mut fixture = Fixture::new();
+    fixture.commit("Only <commit>", &[("a.txt", Some(b"a\n"))]);
+    let state = fixture.open();
+
+    let html = state.get_html_report(None, None).unwrap();
+    assert!(html.contains("This repository has no tags."));
+    assert!(html.contains("No directories in this range."));
+
+    let file_path = fixture.path().join("report.html");
+    let file_path = file_path.to_str().unwrap();
+    assert_eq!(
+        state.export_html_report(file_path, None, None).unwrap(),
+        file_path
+    );
+    assert_eq!(std::fs::read_to_string(file_path).unwrap(), html);
+}
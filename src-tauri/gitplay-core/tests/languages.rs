@@ -0,0 +1,104 @@
+mod common;
+
+use common::Fixture;
+use gitplay_core::languages::LanguageStatsCache;
+use serde_json::Value;
+
+fn get_languages(frames: &Value) -> Vec<Value> {
+    frames
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["languages"].clone())
+        .collect()
+}
+
+#[test]
+fn incremental_stats_equal_a_full_recount() {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "Add sources",
+        &[
+            ("src/main.rs", Some(b"// Entry point\nfn main() {}\n")),
+            ("src/util.rs", Some(b"/* Helpers\n */\n\npub fn add() {}\n")),
+            (
+                "scripts/build",
+                Some(b"#!/usr/bin/env python3\nprint('build')\n"),
+            ),
+            ("README.md", Some(b"# Fixture\n")),
+        ],
+    );
+    fixture.commit(
+        "Grow helpers",
+        &[(
+            "src/util.rs",
+            Some(b"/* Helpers\n */\n\npub fn add() {}\n// Soon\npub fn sub() {}\n"),
+        )],
+    );
+    fixture.rename("Move helpers", "src/util.rs", "src/math/util.rs");
+    // Renaming to another extension moves the file to another language
+    fixture.rename("Rewrite build script", "scripts/build", "scripts/build.py");
+    fixture.commit(
+        "Add a binary and a Python module",
+        &[
+            ("logo.png", Some(b"\x89PNG\0\0\0")),
+            ("tools/gen.py", Some(b"# Generator\n\nimport sys\n")),
+        ],
+    );
+    fixture.commit(
+        "Remove Rust and replace a module with a binary",
+        &[
+            ("src/main.rs", None),
+            ("src/math/util.rs", None),
+            ("tools/gen.py", Some(b"\0\0binary\0")),
+        ],
+    );
+    fixture.commit(
+        "Remove everything",
+        &[("scripts/build.py", None), ("README.md", None)],
+    );
+    let commit_ids = fixture.open().prepare_cache().unwrap().1;
+    assert_eq!(commit_ids.len(), 7);
+
+    let mut incremental = LanguageStatsCache::new();
+    let frames = incremental
+        .get_language_stats(&fixture.repository, &commit_ids, 0, commit_ids.len())
+        .unwrap();
+    let incremental_languages = get_languages(&serde_json::to_value(frames).unwrap());
+
+    for index in 0..commit_ids.len() {
+        // A frame counted on its own is diffed against the empty tree, so every file is counted
+        let frames = LanguageStatsCache::new()
+            .get_language_stats(&fixture.repository, &commit_ids[index..=index], 0, 1)
+            .unwrap();
+        let recount = get_languages(&serde_json::to_value(frames).unwrap());
+        assert_eq!(
+            incremental_languages[index], recount[0],
+            "Frame {} differs from a full recount",
+            index
+        );
+    }
+
+    // Languages without files are dropped
+    assert_eq!(incremental_languages[5].as_object().unwrap().len(), 2);
+    assert_eq!(incremental_languages[6], serde_json::json!({}));
+    assert_eq!(incremental_languages[2]["Rust"]["files"], 2);
+    assert_eq!(incremental_languages[2]["Rust"]["comment_lines"], 4);
+}
+
+#[test]
+fn ranges_of_the_timeline_reuse_earlier_frames() {
+    let mut fixture = Fixture::new();
+    fixture.commit("First", &[("a.rs", Some(b"fn a() {}\n"))]);
+    fixture.commit("Second", &[("b.rs", Some(b"fn b() {}\n"))]);
+    fixture.commit("Third", &[("a.rs", None)]);
+    let state = fixture.open();
+
+    let all = serde_json::to_value(state.get_language_stats(None, None).unwrap()).unwrap();
+    let last = serde_json::to_value(state.get_language_stats(Some(2), Some(1)).unwrap()).unwrap();
+    assert_eq!(last.as_array().unwrap().len(), 1);
+    assert_eq!(last[0]["commit_index"], 2);
+    assert_eq!(last[0], all[2]);
+    assert_eq!(all[1]["languages"]["Rust"]["files"], 2);
+    assert_eq!(all[2]["languages"]["Rust"]["files"], 1);
+}
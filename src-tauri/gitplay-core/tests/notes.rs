@@ -0,0 +1,131 @@
+mod common;
+
+use common::Fixture;
+use git2::Signature;
+use gitplay_core::GitplayState;
+
+#[test]
+fn commit_frames_include_notes_of_configured_refs() {
+    let mut fixture = Fixture::new();
+    let commit_id = fixture.commit("First", &[("a.txt", Some(b"a\n"))]);
+    let signature = Signature::now("Reviewer", "reviewer@example.com").unwrap();
+    fixture
+        .repository
+        .note(&signature, &signature, None, commit_id, "Reviewed", false)
+        .unwrap();
+    fixture
+        .repository
+        .note(
+            &signature,
+            &signature,
+            Some("refs/notes/ci"),
+            commit_id,
+            "Build passed",
+            false,
+        )
+        .unwrap();
+    let state = fixture.open();
+    let commit_id = commit_id.to_string();
+
+    // No notes are read until notes refs are chosen
+    let frame = state.get_commit_details(&commit_id, vec![], false).unwrap();
+    assert!(frame.get_notes().is_empty());
+    assert_eq!(
+        state.list_notes_refs().unwrap(),
+        vec!["refs/notes/ci", "refs/notes/commits"]
+    );
+
+    assert_eq!(
+        state
+            .set_notes_refs(vec!["commits", "refs/notes/ci"])
+            .unwrap(),
+        vec!["refs/notes/commits", "refs/notes/ci"]
+    );
+    let frame = state.get_commit_details(&commit_id, vec![], false).unwrap();
+    let messages: Vec<&str> = frame
+        .get_notes()
+        .iter()
+        .map(|x| x.message.trim_end())
+        .collect();
+    assert_eq!(messages, vec!["Reviewed", "Build passed"]);
+    assert!(state.set_notes_refs(vec!["bad..name"]).is_err());
+}
+
+#[test]
+fn notes_can_be_added_edited_and_removed() {
+    let mut fixture = Fixture::new();
+    let commit_id = fixture
+        .commit("First", &[("a.txt", Some(b"a\n"))])
+        .to_string();
+    let state = fixture.open();
+
+    // Without configured refs, notes go to Git's default ref
+    let notes = state.set_commit_note(&commit_id, None, "Deployed").unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].notes_ref, "refs/notes/commits");
+    assert_eq!(notes[0].message, "Deployed");
+
+    let notes = state
+        .set_commit_note(&commit_id, Some("commits"), "Deployed twice")
+        .unwrap();
+    assert_eq!(notes[0].message, "Deployed twice");
+
+    assert!(state
+        .set_commit_note(&commit_id, None, "")
+        .unwrap()
+        .is_empty());
+    assert!(fixture
+        .repository
+        .find_note(None, git2::Oid::from_str(&commit_id).unwrap())
+        .is_err());
+    assert!(state
+        .set_commit_note("0000000000000000000000000000000000000000", None, "Nope")
+        .is_err());
+}
+
+#[test]
+fn notes_refs_are_saved_in_the_repository_config() {
+    let mut fixture = Fixture::new();
+    let commit_id = fixture.commit("First", &[("a.txt", Some(b"a\n"))]);
+    let signature = Signature::now("Reviewer", "reviewer@example.com").unwrap();
+    fixture
+        .repository
+        .note(
+            &signature,
+            &signature,
+            Some("refs/notes/ci"),
+            commit_id,
+            "Build passed",
+            false,
+        )
+        .unwrap();
+    let state = fixture.open();
+    assert!(state.get_notes_refs().is_empty());
+    state.set_notes_refs(vec!["ci", "review"]).unwrap();
+
+    // Reopening, or opening in a new state, loads the chosen refs again
+    state.open(fixture.path().to_path_buf()).unwrap();
+    assert_eq!(
+        state.get_notes_refs(),
+        vec!["refs/notes/ci", "refs/notes/review"]
+    );
+    let state = fixture.open();
+    assert_eq!(
+        state.get_notes_refs(),
+        vec!["refs/notes/ci", "refs/notes/review"]
+    );
+    let frame = state
+        .get_commit_details(&commit_id.to_string(), vec![], false)
+        .unwrap();
+    assert_eq!(frame.get_notes()[0].message.trim_end(), "Build passed");
+    let config = fixture.repository.config().unwrap().snapshot().unwrap();
+    assert_eq!(
+        config.get_string("gitplay.notesRef").unwrap(),
+        "refs/notes/review"
+    );
+
+    // Choosing no refs clears the config
+    state.set_notes_refs(vec![]).unwrap();
+    assert!(fixture.open().get_notes_refs().is_empty());
+    assert!(GitplayState::new().set_notes_refs(vec!["ci"]).is_err());
+}
@@ -0,0 +1,224 @@
+mod common;
+
+use common::Fixture;
+
+fn grep_fixture() -> Fixture {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "Add sources",
+        &[
+            (
+                "main.rs",
+                Some(b"fn main() {\n    // TODO: parse arguments\n}\n"),
+            ),
+            ("src/lib.rs", Some(b"pub mod parser;\n")),
+            (
+                "src/parser/mod.rs",
+                Some(b"// todo: handle errors\npub fn parse() {}\n"),
+            ),
+            ("docs/notes.md", Some(b"TODO: write the guide\n")),
+            ("logo.png", Some(b"\x89PNG\0\0TODO\0")),
+        ],
+    );
+    fixture
+}
+
+#[test]
+fn grep_limits_files_with_globs() {
+    let fixture = grep_fixture();
+    let state = fixture.open();
+
+    // "*.rs" matches Rust files at any depth, not only at the root
+    let matches = state
+        .grep_at_commit("HEAD", "pub", false, vec!["*.rs"])
+        .unwrap();
+    let paths: Vec<&str> = matches.iter().map(|x| x.path.as_str()).collect();
+    assert_eq!(paths, vec!["src/lib.rs", "src/parser/mod.rs"]);
+    assert_eq!(matches[1].line_number, 2);
+    assert_eq!(matches[1].line, "pub fn parse() {}");
+
+    // Folders are recursive and exclusions remove whole folders
+    let matches = state
+        .grep_at_commit("HEAD", "pub", false, vec!["src", "!src/parser/"])
+        .unwrap();
+    let paths: Vec<&str> = matches.iter().map(|x| x.path.as_str()).collect();
+    assert_eq!(paths, vec!["src/lib.rs"]);
+
+    assert!(state
+        .grep_at_commit("HEAD", "pub", false, vec!["src/[a"])
+        .is_err());
+}
+
+#[test]
+fn grep_is_case_sensitive_unless_asked() {
+    let fixture = grep_fixture();
+    let state = fixture.open();
+
+    let matches = state.grep_at_commit("HEAD", "TODO", false, vec![]).unwrap();
+    let paths: Vec<&str> = matches.iter().map(|x| x.path.as_str()).collect();
+    assert_eq!(paths, vec!["docs/notes.md", "main.rs"]);
+
+    let matches = state
+        .grep_at_commit("HEAD", "(?i)todo:", true, vec![])
+        .unwrap();
+    let paths: Vec<&str> = matches.iter().map(|x| x.path.as_str()).collect();
+    assert_eq!(paths, vec!["docs/notes.md", "main.rs", "src/parser/mod.rs"]);
+    assert_eq!(matches[1].line, "    // TODO: parse arguments");
+    assert_eq!(matches[1].line_number, 2);
+
+    // Without is_regex the pattern is a plain string
+    assert!(state
+        .grep_at_commit("HEAD", "(?i)todo:", false, vec![])
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn grep_skips_binary_files() {
+    let fixture = grep_fixture();
+    let state = fixture.open();
+
+    let matches = state
+        .grep_at_commit("HEAD", "TODO", false, vec!["logo.png"])
+        .unwrap();
+    assert!(matches.is_empty());
+    let matches = state.grep_at_commit("HEAD", "PNG", false, vec![]).unwrap();
+    assert!(matches.is_empty());
+}
+
+fn pickaxe_commit_indices(fixture: &Fixture, pattern: &str, is_regex: bool) -> Vec<usize> {
+    let state = fixture.open();
+    state
+        .find_commits_changing_string(pattern, is_regex, vec![])
+        .unwrap()
+        .iter()
+        .map(|x| x.commit_index)
+        .collect()
+}
+
+#[test]
+fn pickaxe_counts_occurrences_or_matches_lines() {
+    let mut fixture = Fixture::new();
+    fixture.commit("Add parser", &[("a.rs", Some(b"fn parse() {}\n"))]);
+    // Changing the line keeps the number of occurrences, but it is a removed and an added line
+    fixture.commit(
+        "Stub parser",
+        &[("a.rs", Some(b"fn parse() { todo!() }\n"))],
+    );
+    fixture.commit(
+        "Add second parser",
+        &[("a.rs", Some(b"fn parse() { todo!() }\nfn parse_all() {}\n"))],
+    );
+    fixture.commit("Remove parsers", &[("a.rs", Some(b"// Parsing\n"))]);
+
+    assert_eq!(
+        pickaxe_commit_indices(&fixture, "fn parse", false),
+        vec![0, 2, 3]
+    );
+    assert_eq!(
+        pickaxe_commit_indices(&fixture, "fn parse", true),
+        vec![0, 1, 2, 3]
+    );
+    assert_eq!(
+        pickaxe_commit_indices(&fixture, "parse_all", false),
+        vec![2, 3]
+    );
+    assert_eq!(
+        pickaxe_commit_indices(&fixture, r"^fn \w+\(\)", true),
+        vec![0, 1, 2, 3]
+    );
+    // Without is_regex the pattern is a plain string
+    assert!(pickaxe_commit_indices(&fixture, r"fn \w+", false).is_empty());
+
+    let state = fixture.open();
+    assert!(state
+        .find_commits_changing_string("", false, vec![])
+        .is_err());
+    assert!(state
+        .find_commits_changing_string("(", true, vec![])
+        .is_err());
+}
+
+#[test]
+fn pickaxe_limited_to_paths() {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "Add files",
+        &[
+            ("src/a.rs", Some(b"TODO\n")),
+            ("src/gen/b.rs", Some(b"TODO\n")),
+            ("docs/c.md", Some(b"TODO\n")),
+        ],
+    );
+    fixture.commit("Write docs", &[("docs/c.md", Some(b"Done\n"))]);
+    let state = fixture.open();
+
+    let search = |path_filters: Vec<&str>| -> Vec<(usize, Vec<String>)> {
+        state
+            .find_commits_changing_string("TODO", false, path_filters)
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.commit_index, x.paths))
+            .collect()
+    };
+    assert_eq!(
+        search(vec![]),
+        vec![
+            (
+                0,
+                vec![
+                    "docs/c.md".to_owned(),
+                    "src/a.rs".to_owned(),
+                    "src/gen/b.rs".to_owned()
+                ]
+            ),
+            (1, vec!["docs/c.md".to_owned()])
+        ]
+    );
+    assert_eq!(
+        search(vec!["src", "!src/gen/"]),
+        vec![(0, vec!["src/a.rs".to_owned()])]
+    );
+    assert_eq!(
+        search(vec!["*.md"]),
+        vec![
+            (0, vec!["docs/c.md".to_owned()]),
+            (1, vec!["docs/c.md".to_owned()])
+        ]
+    );
+    assert!(state
+        .find_commits_changing_string("TODO", false, vec!["src/[a"])
+        .is_err());
+}
+
+#[test]
+fn pickaxe_leaves_out_merges() {
+    let mut fixture = Fixture::new();
+    fixture.commit("First", &[("a.txt", Some(b"a\n"))]);
+    fixture.branch("feature");
+    fixture.checkout("feature");
+    fixture.commit("Add feature", &[("b.txt", Some(b"feature flag\n"))]);
+    fixture.checkout("main");
+    fixture.commit("Change main", &[("a.txt", Some(b"b\n"))]);
+    fixture.merge(
+        "Merge feature",
+        "feature",
+        &[("b.txt", Some(b"feature flag\n"))],
+    );
+    let state = fixture.open();
+
+    let matches = state
+        .find_commits_changing_string("feature flag", false, vec![])
+        .unwrap();
+    let messages: Vec<String> = matches
+        .iter()
+        .map(|x| {
+            state
+                .get_commit_details(&x.commit_id, vec![], false)
+                .unwrap()
+                .get_message()
+                .to_owned()
+        })
+        .collect();
+    assert_eq!(messages, vec!["Add feature"]);
+}
@@ -0,0 +1,238 @@
+mod common;
+
+use common::Fixture;
+use gitplay_core::cache::GitplayState;
+use serde_json::Value;
+
+// Commit index, kind of event and the other name of a renamed symbol
+fn get_events(state: &GitplayState, name: &str) -> Vec<(usize, String, Option<String>)> {
+    let events = serde_json::to_value(state.get_symbol_timeline(name).unwrap()).unwrap();
+    events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| {
+            let other_name = match (&x["renamed_from"], &x["renamed_to"]) {
+                (Value::String(other_name), _) | (_, Value::String(other_name)) => {
+                    Some(other_name.clone())
+                }
+                _ => None,
+            };
+            (
+                x["commit_index"].as_u64().unwrap() as usize,
+                x["event"].as_str().unwrap().to_owned(),
+                other_name,
+            )
+        })
+        .collect()
+}
+
+fn get_paths(state: &GitplayState, name: &str) -> Vec<Vec<String>> {
+    let events = serde_json::to_value(state.get_symbol_timeline(name).unwrap()).unwrap();
+    events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| {
+            x["locations"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|y| y["path"].as_str().unwrap().to_owned())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn symbol_timeline_of_a_rust_function() {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "Add parser",
+        &[(
+            "src/lib.rs",
+            Some(b"fn parse(input: &str) -> usize {\n    input.len()\n}\n"),
+        )],
+    );
+    fixture.commit(
+        "Count characters",
+        &[(
+            "src/lib.rs",
+            Some(b"fn parse(input: &str) -> usize {\n    input.chars().count()\n}\n"),
+        )],
+    );
+    fixture.commit("Add readme", &[("README.md", Some(b"# Parser\n"))]);
+    fixture.commit(
+        "Rename parser",
+        &[(
+            "src/lib.rs",
+            Some(b"fn count(input: &str) -> usize {\n    input.chars().count()\n}\n"),
+        )],
+    );
+    fixture.commit("Remove parser", &[("src/lib.rs", Some(b"\n"))]);
+    let state = fixture.open();
+
+    assert_eq!(
+        get_events(&state, "parse"),
+        vec![
+            (0, "introduced".to_owned(), None),
+            (1, "modified".to_owned(), None),
+            (3, "renamed".to_owned(), Some("count".to_owned())),
+        ]
+    );
+    assert_eq!(
+        get_events(&state, "count"),
+        vec![
+            (3, "renamed".to_owned(), Some("parse".to_owned())),
+            (4, "deleted".to_owned(), None),
+        ]
+    );
+    // Deleted symbols keep the locations they were deleted from
+    assert_eq!(get_paths(&state, "count")[1], vec!["src/lib.rs"]);
+    assert!(get_events(&state, "missing").is_empty());
+}
+
+#[test]
+fn symbol_timeline_of_a_python_function() {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "Add parser",
+        &[(
+            "app/util.py",
+            Some(b"def parse(text):\n    return text.split()\n"),
+        )],
+    );
+    fixture.commit(
+        "Strip first",
+        &[(
+            "app/util.py",
+            Some(b"def parse(text):\n    return text.strip().split()\n"),
+        )],
+    );
+    fixture.commit(
+        "Move parser",
+        &[
+            ("app/util.py", None),
+            (
+                "app/parsing.py",
+                Some(b"def parse(text):\n    return text.strip().split()\n"),
+            ),
+        ],
+    );
+    fixture.commit(
+        "Rename parser",
+        &[(
+            "app/parsing.py",
+            Some(b"def tokenize(text):\n    return text.strip().split()\n"),
+        )],
+    );
+    fixture.commit("Remove parsing", &[("app/parsing.py", None)]);
+    let state = fixture.open();
+
+    assert_eq!(
+        get_events(&state, "parse"),
+        vec![
+            (0, "introduced".to_owned(), None),
+            (1, "modified".to_owned(), None),
+            (2, "moved".to_owned(), None),
+            (3, "renamed".to_owned(), Some("tokenize".to_owned())),
+        ]
+    );
+    assert_eq!(get_paths(&state, "parse")[2], vec!["app/parsing.py"]);
+    assert_eq!(
+        get_events(&state, "tokenize"),
+        vec![
+            (3, "renamed".to_owned(), Some("parse".to_owned())),
+            (4, "deleted".to_owned(), None),
+        ]
+    );
+}
+
+#[test]
+fn renames_of_recursive_functions_are_found() {
+    // The body calls the function by its own name, which changes with the rename
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "Add factorial",
+        &[(
+            "math.rs",
+            Some(b"fn fact(n: u64) -> u64 {\n    if n == 0 { 1 } else { n * fact(n - 1) }\n}\n"),
+        )],
+    );
+    fixture.commit(
+        "Rename factorial",
+        &[(
+            "math.rs",
+            Some(
+                b"fn factorial(n: u64) -> u64 {\n    if n == 0 { 1 } else { n * factorial(n - 1) }\n}\n",
+            ),
+        )],
+    );
+    let state = fixture.open();
+
+    assert_eq!(
+        get_events(&state, "fact"),
+        vec![
+            (0, "introduced".to_owned(), None),
+            (1, "renamed".to_owned(), Some("factorial".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn the_same_file_is_parsed_for_each_language() {
+    let mut fixture = Fixture::new();
+    let contents: &[u8] = b"def parse(arguments):\n    return arguments\n";
+    fixture.commit(
+        "Add the same file twice",
+        &[("a.py", Some(contents)), ("a.rs", Some(contents))],
+    );
+    let state = fixture.open();
+
+    let files = serde_json::to_value(state.get_symbols_at_commit("HEAD", vec![]).unwrap()).unwrap();
+    let names = |path: &str| -> Vec<String> {
+        files
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|x| x["path"] == path)
+            .flat_map(|x| x["symbols"].as_array().unwrap().clone())
+            .map(|x| x["name"].as_str().unwrap().to_owned())
+            .collect()
+    };
+    assert_eq!(names("a.py"), vec!["parse"]);
+    // Python is not Rust, whatever was parsed for the same blob before
+    assert!(names("a.rs").is_empty());
+}
+
+#[test]
+fn code_added_above_a_symbol_does_not_modify_it() {
+    let mut fixture = Fixture::new();
+    fixture.commit(
+        "Add parser",
+        &[("lib.rs", Some(b"fn parse() -> usize {\n    1\n}\n"))],
+    );
+    fixture.commit(
+        "Add imports",
+        &[(
+            "lib.rs",
+            Some(b"use std::io;\nuse std::fs;\n\nfn parse() -> usize {\n    1\n}\n"),
+        )],
+    );
+    fixture.commit(
+        "Change parser",
+        &[(
+            "lib.rs",
+            Some(b"use std::io;\nuse std::fs;\n\nfn parse() -> usize {\n    2\n}\n"),
+        )],
+    );
+    let state = fixture.open();
+
+    assert_eq!(
+        get_events(&state, "parse"),
+        vec![
+            (0, "introduced".to_owned(), None),
+            (2, "modified".to_owned(), None),
+        ]
+    );
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn tags_are_resolved_to_frames_of_the_timeline() {
+    let mut fixture = Fixture::new();
+    fixture.commit("First", &[("a.txt", Some(b"1\n"))]);
+    fixture.tag("v1.2", None);
+    fixture.commit("Second", &[("a.txt", Some(b"2\n"))]);
+    fixture.commit("Third", &[("a.txt", Some(b"3\n"))]);
+    fixture.tag("v1.3", Some("Release 1.3\n\nFaster and smaller"));
+    // A tag on a branch which is not checked out is not in the timeline
+    fixture.branch("experiment");
+    fixture.checkout("experiment");
+    let experiment_id = fixture.commit("Experiment", &[("b.txt", Some(b"b\n"))]);
+    fixture.tag("experiment-1", None);
+    fixture.checkout("main");
+    let state = fixture.open();
+
+    let tags = state.get_tags().unwrap();
+    let names: Vec<&str> = tags.iter().map(|x| x.name.as_str()).collect();
+    assert_eq!(names, vec!["v1.2", "v1.3", "experiment-1"]);
+
+    assert_eq!(tags[0].commit_index, Some(0));
+    assert!(!tags[0].is_annotated);
+    assert_eq!(tags[0].tagger, None);
+    assert_eq!(tags[0].message, None);
+
+    assert_eq!(tags[1].commit_index, Some(2));
+    assert!(tags[1].is_annotated);
+    assert_eq!(tags[1].tagger.as_deref(), Some("Fixture"));
+    assert_eq!(tags[1].tagger_email.as_deref(), Some("fixture@example.com"));
+    assert!(tags[1].tag_time.is_some());
+    assert!(tags[1]
+        .message
+        .as_deref()
+        .unwrap()
+        .starts_with("Release 1.3"));
+
+    assert_eq!(tags[2].commit_id, experiment_id.to_string());
+    assert_eq!(tags[2].commit_index, None);
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::Fixture;
+use gitplay_core::tours::{OpenedFile, TourStep};
+
+fn step(commit_id: &str, note: &str) -> TourStep {
+    TourStep {
+        commit_id: commit_id.to_owned(),
+        note: note.to_owned(),
+        ..TourStep::default()
+    }
+}
+
+#[test]
+fn record_and_play_tour() {
+    let mut fixture = Fixture::new();
+    let first = fixture.commit("First", &[("src/main.rs", Some(b"fn main() {}\n"))]);
+    let second = fixture.commit("Second", &[("src/lib.rs", Some(b"pub mod a;\n"))]);
+    let state = fixture.open();
+
+    let active_tour = state.create_tour("The Great Refactor!", None).unwrap();
+    assert!(active_tour
+        .file_path
+        .ends_with(".gitplay/tours/the-great-refactor.json"));
+    assert!(state.create_tour("The great refactor", None).is_err());
+
+    state
+        .add_tour_step(step(&second.to_string(), "Then a library"), None)
+        .unwrap();
+    state
+        .add_tour_step(step(&first.to_string(), "It starts here"), Some(0))
+        .unwrap();
+    state
+        .update_tour("The great refactor", "How *it* began")
+        .unwrap();
+    assert_eq!(state.list_tours().unwrap()[0].steps_count, 2);
+
+    // A new session plays the tour from its file
+    let state = fixture.open();
+    let active_tour = state
+        .load_tour(&state.list_tours().unwrap()[0].file_path)
+        .unwrap();
+    assert_eq!(active_tour.tour.description, "How *it* began");
+    assert_eq!(active_tour.current_step, None);
+    assert!(state.previous_tour_step().is_err());
+
+    let view = state.next_tour_step().unwrap();
+    assert_eq!((view.step_index, view.steps_count), (0, 2));
+    assert_eq!(view.step.note, "It starts here");
+    assert_eq!(view.commit_index, Some(0));
+    let view = state.next_tour_step().unwrap();
+    assert_eq!((view.step_index, view.commit_index), (1, Some(1)));
+    assert!(state.next_tour_step().is_err());
+    assert_eq!(state.previous_tour_step().unwrap().step_index, 0);
+
+    state.remove_tour_step(0).unwrap();
+    let active_tour = state.get_active_tour().unwrap();
+    assert_eq!(active_tour.tour.steps.len(), 1);
+    assert_eq!(active_tour.current_step, Some(0));
+    assert!(state.remove_tour_step(1).is_err());
+}
+
+#[test]
+fn validate_tour_finds_missing_commits_files_and_lines() {
+    let mut fixture = Fixture::new();
+    let commit_id = fixture
+        .commit("First", &[("src/main.rs", Some(b"fn main() {\n}\n"))])
+        .to_string();
+    let state = fixture.open();
+    let file_path = fixture.path().join("outside").join("tour.json");
+    state
+        .create_tour("Checks", Some(file_path.to_str().unwrap()))
+        .unwrap();
+
+    let valid = TourStep {
+        open_folders: vec!["src/".to_owned()],
+        opened_file: Some(OpenedFile {
+            path: "src/main.rs".to_owned(),
+            start_line: Some(1),
+            end_line: Some(2),
+        }),
+        ..step(&commit_id, "")
+    };
+    let missing_lines = TourStep {
+        opened_file: Some(OpenedFile {
+            path: "src/main.rs".to_owned(),
+            start_line: Some(2),
+            end_line: Some(5),
+        }),
+        ..step(&commit_id, "")
+    };
+    let missing_paths = TourStep {
+        open_folders: vec!["docs/".to_owned()],
+        opened_file: Some(OpenedFile {
+            path: "README.md".to_owned(),
+            start_line: None,
+            end_line: None,
+        }),
+        ..step(&commit_id, "")
+    };
+    for step in [
+        valid,
+        missing_lines,
+        missing_paths,
+        step("0123456789012345678901234567890123456789", ""),
+    ] {
+        state.add_tour_step(step, None).unwrap();
+    }
+
+    let problems: Vec<(usize, String)> = state
+        .validate_tour()
+        .unwrap()
+        .into_iter()
+        .map(|x| (x.step_index, x.message))
+        .collect();
+    assert_eq!(
+        problems,
+        vec![
+            (
+                1,
+                "File src/main.rs has only 2 lines at this commit".to_owned()
+            ),
+            (2, "Folder docs/ does not exist at this commit".to_owned()),
+            (2, "File README.md does not exist at this commit".to_owned()),
+            (
+                3,
+                "Commit 0123456789012345678901234567890123456789 does not exist".to_owned()
+            ),
+        ]
+    );
+}
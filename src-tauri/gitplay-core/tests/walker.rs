@@ -0,0 +1,227 @@
+mod common;
+
+use common::Fixture;
+use gitplay_core::walker::{get_commit_details, load_all_commits, read_file_contents, FileBlob};
+
+fn find_blob<'a>(blobs: &'a [FileBlob], path: &str, name: &str) -> &'a FileBlob {
+    blobs
+        .iter()
+        .find(|x| x.get_path() == path && x.get_name() == name)
+        .unwrap_or_else(|| panic!("{}{} is not in the tree", path, name))
+}
+
+#[test]
+fn load_all_commits_is_oldest_first() {
+    let mut fixture = Fixture::new();
+    let first = fixture.commit("Add README", &[("README.md", Some(b"# Fixture\n"))]);
+    let second = fixture.commit("Add main", &[("src/main.rs", Some(b"fn main() {}\n"))]);
+    let third = fixture.commit("Remove README", &[("README.md", None)]);
+
+    let commits = load_all_commits(&fixture.repository).unwrap();
+    let ids: Vec<String> = commits.iter().map(|x| x.get_id()).collect();
+    assert_eq!(
+        ids,
+        vec![first.to_string(), second.to_string(), third.to_string()]
+    );
+    assert_eq!(commits[1].get_message(), "Add main");
+    assert_eq!(commits[1].get_parents(), &[first.to_string()]);
+    assert!(commits[0].get_parents().is_empty());
+    assert!(commits[0].get_time() < commits[1].get_time());
+    // The timeline is loaded without file trees
+    assert!(commits[2].get_file_structure().is_none());
+}
+
+#[test]
+fn load_all_commits_includes_merged_branches() {
+    let mut fixture = Fixture::new();
+    let base = fixture.commit("Base", &[("a.txt", Some(b"a\n"))]);
+    fixture.branch("feature");
+    fixture.checkout("feature");
+    let feature = fixture.commit("Feature", &[("b.txt", Some(b"b\n"))]);
+    fixture.checkout("main");
+    let main = fixture.commit("Main", &[("c.txt", Some(b"c\n"))]);
+    let merge = fixture.merge("Merge feature", "feature", &[("b.txt", Some(b"b\n"))]);
+
+    let commits = load_all_commits(&fixture.repository).unwrap();
+    let ids: Vec<String> = commits.iter().map(|x| x.get_id()).collect();
+    assert_eq!(ids.len(), 4);
+    assert_eq!(ids[0], base.to_string());
+    assert!(ids.contains(&feature.to_string()));
+    assert!(ids.contains(&main.to_string()));
+    assert_eq!(ids[3], merge.to_string());
+    assert_eq!(
+        commits[3].get_parents(),
+        &[main.to_string(), feature.to_string()]
+    );
+}
+
+#[test]
+fn load_all_commits_of_empty_repository() {
+    let fixture = Fixture::new();
+    assert!(load_all_commits(&fixture.repository).unwrap().is_empty());
+}
+
+#[test]
+fn load_all_commits_from_detached_head() {
+    let mut fixture = Fixture::new();
+    let first = fixture.commit("First", &[("a.txt", Some(b"1\n"))]);
+    let second = fixture.commit("Second", &[("a.txt", Some(b"2\n"))]);
+    fixture.commit("Third", &[("a.txt", Some(b"3\n"))]);
+    fixture.detach_head(second);
+
+    // The timeline ends at the commit HEAD is detached at
+    let commits = load_all_commits(&fixture.repository).unwrap();
+    let ids: Vec<String> = commits.iter().map(|x| x.get_id()).collect();
+    assert_eq!(ids, vec![first.to_string(), second.to_string()]);
+}
+
+#[test]
+fn load_all_commits_with_non_utf8_message() {
+    let mut fixture = Fixture::new();
+    fixture.commit_with_message_bytes(b"Caf\xe9 au lait", &[("a.txt", Some(b"a\n"))]);
+
+    let commits = load_all_commits(&fixture.repository).unwrap();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].get_message(), "Caf\u{fffd} au lait");
+}
+
+#[test]
+fn get_commit_details_lists_requested_folders() {
+    let mut fixture = Fixture::new();
+    let commit_id = fixture.commit(
+        "Add files",
+        &[
+            ("README.md", Some(b"# Fixture\n\nA test\n")),
+            ("src/main.rs", Some(b"fn main() {}\n")),
+            ("src/lib/mod.rs", Some(b"pub mod a;\npub mod b;\n")),
+        ],
+    );
+
+    let frame = get_commit_details(
+        &fixture.repository,
+        &commit_id.to_string(),
+        true,
+        Some(vec![""]),
+        true,
+    )
+    .unwrap();
+    assert_eq!(frame.get_message(), "Add files");
+    let blobs = frame.get_file_structure().unwrap().get_blobs();
+    assert_eq!(blobs.len(), 2);
+    let readme = find_blob(blobs, "", "README.md");
+    assert!(!readme.is_directory());
+    assert_eq!(readme.get_size(), 18);
+    assert_eq!(readme.get_line_count(), Some(3));
+    // Directories are aggregated over all the files within
+    let src = find_blob(blobs, "", "src");
+    assert!(src.is_directory());
+    assert_eq!(src.get_size(), 13 + 22);
+    assert_eq!(src.get_file_count(), 2);
+    assert_eq!(src.get_line_count(), Some(3));
+
+    let frame = get_commit_details(
+        &fixture.repository,
+        &commit_id.to_string(),
+        true,
+        Some(vec!["src/"]),
+        false,
+    )
+    .unwrap();
+    let blobs = frame.get_file_structure().unwrap().get_blobs();
+    assert_eq!(blobs.len(), 2);
+    assert!(!find_blob(blobs, "src/", "main.rs").is_directory());
+    let lib = find_blob(blobs, "src/", "lib");
+    assert!(lib.is_directory());
+    // Lines are only counted when asked for
+    assert_eq!(lib.get_size(), 22);
+    assert_eq!(lib.get_line_count(), None);
+}
+
+#[test]
+fn get_commit_details_after_rename() {
+    let mut fixture = Fixture::new();
+    fixture.commit("Add notes", &[("notes.txt", Some(b"Some notes\n"))]);
+    let before = fixture.read_at_head("notes.txt");
+    let commit_id = fixture.rename("Move notes", "notes.txt", "docs/notes.txt");
+
+    let frame = get_commit_details(
+        &fixture.repository,
+        &commit_id.to_string(),
+        true,
+        Some(vec!["", "docs/"]),
+        false,
+    )
+    .unwrap();
+    let blobs = frame.get_file_structure().unwrap().get_blobs();
+    assert!(!blobs
+        .iter()
+        .any(|x| x.get_name() == "notes.txt" && x.get_path().is_empty()));
+    let moved = find_blob(blobs, "docs/", "notes.txt");
+    assert_eq!(
+        read_file_contents(&fixture.repository, moved.get_object_id()).unwrap(),
+        String::from_utf8(before).unwrap()
+    );
+}
+
+#[test]
+fn get_commit_details_of_binary_file() {
+    let mut fixture = Fixture::new();
+    let contents: &[u8] = &[0x89, b'P', b'N', b'G', 0, 0, 0, 0x0d, 0xff, 0xfe];
+    let commit_id = fixture.commit("Add image", &[("logo.png", Some(contents))]);
+
+    let frame =
+        get_commit_details(&fixture.repository, "HEAD", true, Some(vec![""]), true).unwrap();
+    assert_eq!(frame.get_id(), "HEAD");
+    let logo = find_blob(
+        frame.get_file_structure().unwrap().get_blobs(),
+        "",
+        "logo.png",
+    );
+    assert_eq!(logo.get_size(), contents.len());
+    // Binary files have no lines
+    assert_eq!(logo.get_line_count(), Some(0));
+    assert!(read_file_contents(&fixture.repository, logo.get_object_id()).is_err());
+
+    assert!(get_commit_details(
+        &fixture.repository,
+        &commit_id.to_string(),
+        false,
+        None,
+        false
+    )
+    .unwrap()
+    .get_file_structure()
+    .is_none());
+}
+
+#[test]
+fn get_commit_details_of_unknown_revision() {
+    let mut fixture = Fixture::new();
+    fixture.commit("First", &[("a.txt", Some(b"a\n"))]);
+    assert!(get_commit_details(&fixture.repository, "no-such-branch", true, None, false).is_err());
+}
+
+#[test]
+fn read_file_contents_of_text_file() {
+    let mut fixture = Fixture::new();
+    fixture.commit("Add file", &[("a.txt", Some(b"first line\nsecond line\n"))]);
+    let blob_id = fixture
+        .repository
+        .revparse_single("HEAD:a.txt")
+        .unwrap()
+        .id()
+        .to_string();
+
+    assert_eq!(
+        read_file_contents(&fixture.repository, &blob_id).unwrap(),
+        "first line\nsecond line\n"
+    );
+    // Trees are not files
+    let tree_id = fixture
+        .repository
+        .revparse_single("HEAD^{tree}")
+        .unwrap()
+        .id()
+        .to_string();
+    assert!(read_file_contents(&fixture.repository, &tree_id).is_err());
+}
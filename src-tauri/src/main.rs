@@ -3,12 +3,21 @@
 
 use std::{collections::HashMap, path::PathBuf};
 
-use cache::GitplayState;
+use gitplay_core::{
+    annotations::{Annotation, AnnotationTarget},
+    buckets::{BucketSize, TimeBucket},
+    cache::GitplayState,
+    diff::{CommitDiff, FileChurn, MergeDiffMode},
+    graph::GraphRow,
+    languages::FrameLanguageStats,
+    notes::CommitNote,
+    search::{GrepMatch, PickaxeMatch},
+    symbols::{FileSymbols, SymbolEvent},
+    tags::Tag,
+    tours::{ActiveTour, TourFile, TourProblem, TourStep, TourStepView},
+    walker::{CommitFrame, CommitSummary},
+};
 use tauri::{self, State};
-use walker::CommitFrame;
-
-mod cache;
-mod walker;
 
 #[tauri::command]
 async fn open_repository(path: &str, repo: State<'_, GitplayState>) -> Result<String, String> {
@@ -37,20 +46,89 @@ async fn get_commits(
     output
 }
 
+#[tauri::command]
+async fn get_commit_summaries(
+    start_index: Option<usize>,
+    count: Option<usize>,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<CommitSummary>, String> {
+    let output = repo.get_commit_summaries(start_index, count);
+    println!(
+        "get_commit_summaries {:?}, from {:?}, completed",
+        start_index, count
+    );
+    output
+}
+
+#[tauri::command]
+async fn get_commit_graph(
+    start_index: Option<usize>,
+    count: Option<usize>,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<GraphRow>, String> {
+    let output = repo.get_commit_graph(start_index, count);
+    println!(
+        "get_commit_graph {:?}, from {:?}, completed",
+        start_index, count
+    );
+    output
+}
+
+#[tauri::command]
+async fn find_commit_at_time(
+    timestamp: i64,
+    repo: State<'_, GitplayState>,
+) -> Result<Option<usize>, String> {
+    let output = repo.find_commit_at_time(timestamp);
+    println!("find_commit_at_time, {:?} completed", timestamp);
+    output
+}
+
+#[tauri::command]
+async fn get_time_buckets(
+    size: BucketSize,
+    include_empty: Option<bool>,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<TimeBucket>, String> {
+    let output = repo.get_time_buckets(size, include_empty.unwrap_or(false));
+    println!(
+        "get_time_buckets, {:?}, {:?} completed",
+        size, include_empty
+    );
+    output
+}
+
 #[tauri::command]
 async fn get_commit_details(
     commit_id: &str,
     requested_folders: Vec<&str>,
+    with_line_counts: Option<bool>,
     repo: State<'_, GitplayState>,
 ) -> Result<CommitFrame, String> {
-    let output = repo.get_commit_details(commit_id, requested_folders.clone());
+    let output = repo.get_commit_details(
+        commit_id,
+        requested_folders.clone(),
+        with_line_counts.unwrap_or(false),
+    );
     println!(
-        "get_commit_details, {:?}, {:?} completed",
-        commit_id, requested_folders
+        "get_commit_details, {:?}, {:?}, {:?} completed",
+        commit_id, requested_folders, with_line_counts
     );
     output
 }
 
+#[tauri::command]
+async fn get_commit_diff(
+    commit_id: &str,
+    mode: Option<MergeDiffMode>,
+    repo: State<'_, GitplayState>,
+) -> Result<CommitDiff, String> {
+    let mode = mode.unwrap_or_default();
+    let output = repo.get_commit_diff(commit_id, mode);
+    println!("get_commit_diff, {:?}, {:?} completed", commit_id, mode);
+    output
+}
+
 #[tauri::command]
 async fn read_file_contents(
     object_id: &str,
@@ -89,6 +167,344 @@ async fn get_files_ordered_by_most_modifications(
     output
 }
 
+#[tauri::command]
+async fn get_file_history(
+    file_path: &str,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<CommitSummary>, String> {
+    let output = repo.get_file_history(file_path);
+    println!("get_file_history, {:?} completed", file_path);
+    output
+}
+
+#[tauri::command]
+async fn get_file_churn(
+    start_index: Option<usize>,
+    count: Option<usize>,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<FileChurn>, String> {
+    let output = repo.get_file_churn(start_index, count);
+    println!(
+        "get_file_churn, from {:?}, {:?} completed",
+        start_index, count
+    );
+    output
+}
+
+#[tauri::command]
+async fn find_commits_changing_string(
+    pattern: &str,
+    is_regex: bool,
+    path_filters: Vec<&str>,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<PickaxeMatch>, String> {
+    let output = repo.find_commits_changing_string(pattern, is_regex, path_filters.clone());
+    println!(
+        "find_commits_changing_string, {:?}, {:?} completed",
+        pattern, path_filters
+    );
+    output
+}
+
+#[tauri::command]
+async fn grep_at_commit(
+    commit_id: &str,
+    pattern: &str,
+    is_regex: bool,
+    path_globs: Vec<&str>,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<GrepMatch>, String> {
+    let output = repo.grep_at_commit(commit_id, pattern, is_regex, path_globs.clone());
+    println!(
+        "grep_at_commit, {:?}, {:?}, {:?} completed",
+        commit_id, pattern, path_globs
+    );
+    output
+}
+
+#[tauri::command]
+async fn get_symbols_at_commit(
+    commit_id: &str,
+    path_filters: Vec<&str>,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<FileSymbols>, String> {
+    let output = repo.get_symbols_at_commit(commit_id, path_filters.clone());
+    println!(
+        "get_symbols_at_commit, {:?}, {:?} completed",
+        commit_id, path_filters
+    );
+    output
+}
+
+#[tauri::command]
+async fn get_symbol_timeline(
+    name: &str,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<SymbolEvent>, String> {
+    let output = repo.get_symbol_timeline(name);
+    println!("get_symbol_timeline, {:?} completed", name);
+    output
+}
+
+#[tauri::command]
+async fn get_language_stats(
+    start_index: Option<usize>,
+    count: Option<usize>,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<FrameLanguageStats>, String> {
+    let output = repo.get_language_stats(start_index, count);
+    println!(
+        "get_language_stats, from {:?}, {:?} completed",
+        start_index, count
+    );
+    output
+}
+
+#[tauri::command]
+async fn export_gource_log(
+    file_path: &str,
+    start_index: Option<usize>,
+    count: Option<usize>,
+    repo: State<'_, GitplayState>,
+) -> Result<usize, String> {
+    let output = repo.export_gource_log(file_path, start_index, count);
+    println!(
+        "export_gource_log, {:?}, from {:?}, {:?} completed",
+        file_path, start_index, count
+    );
+    output
+}
+
+#[tauri::command]
+async fn export_report(
+    folder_path: &str,
+    start_index: Option<usize>,
+    count: Option<usize>,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<String>, String> {
+    let output = repo.export_report(folder_path, start_index, count);
+    println!(
+        "export_report, {:?}, from {:?}, {:?} completed",
+        folder_path, start_index, count
+    );
+    output
+}
+
+#[tauri::command]
+async fn export_html_report(
+    file_path: &str,
+    start_index: Option<usize>,
+    count: Option<usize>,
+    repo: State<'_, GitplayState>,
+) -> Result<String, String> {
+    let output = repo.export_html_report(file_path, start_index, count);
+    println!(
+        "export_html_report, {:?}, from {:?}, {:?} completed",
+        file_path, start_index, count
+    );
+    output
+}
+
+#[tauri::command]
+async fn list_tours(repo: State<'_, GitplayState>) -> Result<Vec<TourFile>, String> {
+    let output = repo.list_tours();
+    println!("list_tours completed");
+    output
+}
+
+#[tauri::command]
+async fn create_tour(
+    name: &str,
+    file_path: Option<&str>,
+    repo: State<'_, GitplayState>,
+) -> Result<ActiveTour, String> {
+    let output = repo.create_tour(name, file_path);
+    println!("create_tour, {:?}, {:?} completed", name, file_path);
+    output
+}
+
+#[tauri::command]
+async fn load_tour(file_path: &str, repo: State<'_, GitplayState>) -> Result<ActiveTour, String> {
+    let output = repo.load_tour(file_path);
+    println!("load_tour, {:?} completed", file_path);
+    output
+}
+
+#[tauri::command]
+async fn get_active_tour(repo: State<'_, GitplayState>) -> Result<Option<ActiveTour>, String> {
+    let output = repo.get_active_tour();
+    println!("get_active_tour completed");
+    Ok(output)
+}
+
+#[tauri::command]
+async fn update_tour(
+    name: &str,
+    description: &str,
+    repo: State<'_, GitplayState>,
+) -> Result<ActiveTour, String> {
+    let output = repo.update_tour(name, description);
+    println!("update_tour, {:?} completed", name);
+    output
+}
+
+#[tauri::command]
+async fn add_tour_step(
+    step: TourStep,
+    step_index: Option<usize>,
+    repo: State<'_, GitplayState>,
+) -> Result<ActiveTour, String> {
+    let output = repo.add_tour_step(step, step_index);
+    println!("add_tour_step, {:?} completed", step_index);
+    output
+}
+
+#[tauri::command]
+async fn update_tour_step(
+    step_index: usize,
+    step: TourStep,
+    repo: State<'_, GitplayState>,
+) -> Result<ActiveTour, String> {
+    let output = repo.update_tour_step(step_index, step);
+    println!("update_tour_step, {:?} completed", step_index);
+    output
+}
+
+#[tauri::command]
+async fn remove_tour_step(
+    step_index: usize,
+    repo: State<'_, GitplayState>,
+) -> Result<ActiveTour, String> {
+    let output = repo.remove_tour_step(step_index);
+    println!("remove_tour_step, {:?} completed", step_index);
+    output
+}
+
+#[tauri::command]
+async fn validate_tour(repo: State<'_, GitplayState>) -> Result<Vec<TourProblem>, String> {
+    let output = repo.validate_tour();
+    println!("validate_tour completed");
+    output
+}
+
+#[tauri::command]
+async fn go_to_tour_step(
+    step_index: usize,
+    repo: State<'_, GitplayState>,
+) -> Result<TourStepView, String> {
+    let output = repo.go_to_tour_step(step_index);
+    println!("go_to_tour_step, {:?} completed", step_index);
+    output
+}
+
+#[tauri::command]
+async fn next_tour_step(repo: State<'_, GitplayState>) -> Result<TourStepView, String> {
+    let output = repo.next_tour_step();
+    println!("next_tour_step completed");
+    output
+}
+
+#[tauri::command]
+async fn previous_tour_step(repo: State<'_, GitplayState>) -> Result<TourStepView, String> {
+    let output = repo.previous_tour_step();
+    println!("previous_tour_step completed");
+    output
+}
+
+#[tauri::command]
+async fn list_annotations(repo: State<'_, GitplayState>) -> Result<Vec<Annotation>, String> {
+    let output = repo.list_annotations();
+    println!("list_annotations completed");
+    output
+}
+
+#[tauri::command]
+async fn get_annotations_at_commit(
+    commit_index: usize,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<Annotation>, String> {
+    let output = repo.get_annotations_at_commit(commit_index);
+    println!("get_annotations_at_commit, {:?} completed", commit_index);
+    output
+}
+
+#[tauri::command]
+async fn add_annotation(
+    target: AnnotationTarget,
+    text: &str,
+    shared: bool,
+    repo: State<'_, GitplayState>,
+) -> Result<Annotation, String> {
+    let output = repo.add_annotation(target.clone(), text, shared);
+    println!("add_annotation, {:?}, {:?} completed", target, shared);
+    output
+}
+
+#[tauri::command]
+async fn update_annotation(
+    id: &str,
+    text: &str,
+    shared: bool,
+    repo: State<'_, GitplayState>,
+) -> Result<Annotation, String> {
+    let output = repo.update_annotation(id, text, shared);
+    println!("update_annotation, {:?}, {:?} completed", id, shared);
+    output
+}
+
+#[tauri::command]
+async fn remove_annotation(id: &str, repo: State<'_, GitplayState>) -> Result<Annotation, String> {
+    let output = repo.remove_annotation(id);
+    println!("remove_annotation, {:?} completed", id);
+    output
+}
+
+#[tauri::command]
+async fn get_notes_refs(repo: State<'_, GitplayState>) -> Result<Vec<String>, String> {
+    let output = repo.get_notes_refs();
+    println!("get_notes_refs completed");
+    Ok(output)
+}
+
+#[tauri::command]
+async fn set_notes_refs(
+    notes_refs: Vec<&str>,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<String>, String> {
+    let output = repo.set_notes_refs(notes_refs.clone());
+    println!("set_notes_refs, {:?} completed", notes_refs);
+    output
+}
+
+#[tauri::command]
+async fn list_notes_refs(repo: State<'_, GitplayState>) -> Result<Vec<String>, String> {
+    let output = repo.list_notes_refs();
+    println!("list_notes_refs completed");
+    output
+}
+
+#[tauri::command]
+async fn set_commit_note(
+    commit_id: &str,
+    notes_ref: Option<&str>,
+    message: &str,
+    repo: State<'_, GitplayState>,
+) -> Result<Vec<CommitNote>, String> {
+    let output = repo.set_commit_note(commit_id, notes_ref, message);
+    println!(
+        "set_commit_note, {:?}, {:?} completed",
+        commit_id, notes_ref
+    );
+    output
+}
+
+#[tauri::command]
+async fn get_tags(repo: State<'_, GitplayState>) -> Result<Vec<Tag>, String> {
+    let output = repo.get_tags();
+    println!("get_tags completed");
+    output
+}
+
 fn main() {
     tauri::Builder::default()
         .manage(GitplayState::new())
@@ -96,10 +512,47 @@ fn main() {
             open_repository,
             prepare_cache,
             get_commits,
+            get_commit_summaries,
+            get_commit_graph,
+            get_time_buckets,
+            find_commit_at_time,
             get_commit_details,
+            get_commit_diff,
             read_file_contents,
             get_sizes_for_paths,
-            get_files_ordered_by_most_modifications
+            get_files_ordered_by_most_modifications,
+            get_file_history,
+            get_file_churn,
+            find_commits_changing_string,
+            grep_at_commit,
+            get_symbols_at_commit,
+            get_symbol_timeline,
+            get_language_stats,
+            export_gource_log,
+            export_report,
+            export_html_report,
+            list_tours,
+            create_tour,
+            load_tour,
+            get_active_tour,
+            update_tour,
+            add_tour_step,
+            update_tour_step,
+            remove_tour_step,
+            validate_tour,
+            go_to_tour_step,
+            next_tour_step,
+            previous_tour_step,
+            list_annotations,
+            get_annotations_at_commit,
+            add_annotation,
+            update_annotation,
+            remove_annotation,
+            get_notes_refs,
+            set_notes_refs,
+            list_notes_refs,
+            set_commit_note,
+            get_tags
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -12,6 +12,8 @@ interface IAPIFileBlob {
   name: string;
   is_directory: boolean;
   size?: number;
+  file_count?: number;
+  line_count?: number;
 }
 
 interface IAPIFileTree {
@@ -19,12 +21,26 @@ interface IAPIFileTree {
   blobs: Array<IAPIFileBlob>;
 }
 
+interface IAPICommitNote {
+  notes_ref: string;
+  message: string;
+}
+
+interface IAPIDecoration {
+  name: string;
+  kind: "head" | "branch" | "remote_branch" | "tag";
+  is_head: boolean;
+}
+
 interface IAPICommitFrame {
   commit_id: string;
   commit_message: string;
+  author: string;
   time: number;
   file_structure?: IAPIFileTree;
   parents: Array<string>;
+  notes: Array<IAPICommitNote>;
+  decorations: Array<IAPIDecoration>;
 }
 
 const isIAPICommitFrame = (data: unknown): data is IAPICommitFrame => {
@@ -76,6 +92,8 @@ export type {
   IAPIFileBlob,
   IAPIFileTree,
   IAPICommitFrame,
+  IAPICommitNote,
+  IAPIDecoration,
   IFileBlob,
   IFileTree,
   ICommitFrame,